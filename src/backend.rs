@@ -0,0 +1,181 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+use std::io::Error as IoError;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::str::FromStr;
+
+use crc32fast::Hasher;
+
+use super::Crc32;
+
+/// Use a 64k buffer size for better performance.
+pub const DEFAULT_BUFFER_SIZE: usize = 65536;
+
+/// The minimum size of a chunk hashed by a thread of the parallel backend.
+const MIN_CHUNK_SIZE: u64 = 1 << 24;
+
+/// A callback receiving the number of bytes read after each block.
+pub(crate) type OnBlock<'a> = dyn Fn(usize) + Sync + 'a;
+
+/// A strategy to read the content of a file and compute its CRC32.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Read the file sequentially using a buffer of the given size.
+    Read(usize),
+    /// Map the file in memory with the `mmap` syscall.
+    #[cfg(feature = "mmap")]
+    Mmap,
+    /// Hash chunks of the file in parallel using the given number of threads.
+    Parallel(usize),
+}
+
+impl Backend {
+    /// Get all the backends worth comparing on the current host.
+    pub fn candidates() -> Vec<Backend> {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let mut candidates = vec![
+            Backend::Read(1 << 12),
+            Backend::Read(DEFAULT_BUFFER_SIZE),
+            Backend::Read(1 << 20),
+        ];
+        #[cfg(feature = "mmap")]
+        candidates.push(Backend::Mmap);
+        if threads > 1 {
+            candidates.push(Backend::Parallel(2));
+        }
+        if threads > 2 {
+            candidates.push(Backend::Parallel(threads));
+        }
+        candidates
+    }
+
+    /// Compute the CRC32 of the file at the given path.
+    ///
+    /// The number of bytes read is reported to `on_block` after each block,
+    /// which may be called from several threads at once. Without a callback,
+    /// the `mmap` backend hashes the whole mapping at once.
+    pub(crate) fn compute(
+        &self,
+        path: &Path,
+        on_block: Option<&OnBlock<'_>>,
+    ) -> Result<Crc32, IoError> {
+        match *self {
+            Backend::Read(buffer_size) => {
+                let mut file = File::open(path)?;
+                let hasher = hash_read(&mut file, buffer_size, u64::MAX, on_block)?;
+                Ok(hasher.finalize())
+            }
+            #[cfg(feature = "mmap")]
+            Backend::Mmap => {
+                let file = File::open(path)?;
+                let mut hasher = Hasher::new();
+                // mapping an empty file is an error on some platforms
+                if file.metadata()?.len() > 0 {
+                    let mmap = unsafe { memmap::MmapOptions::new().map(&file)? };
+                    match on_block {
+                        None => hasher.update(&mmap[..]),
+                        Some(on_block) => {
+                            for block in mmap.chunks(DEFAULT_BUFFER_SIZE) {
+                                hasher.update(block);
+                                on_block(block.len());
+                            }
+                        }
+                    }
+                }
+                Ok(hasher.finalize())
+            }
+            Backend::Parallel(threads) => {
+                let len = std::fs::metadata(path)?.len();
+                let chunk_size = std::cmp::max(MIN_CHUNK_SIZE, len / threads.max(1) as u64 + 1);
+                std::thread::scope(|scope| {
+                    // hash each chunk in its own thread
+                    let handles = (0..len.div_ceil(chunk_size))
+                        .map(|i| {
+                            scope.spawn(move || {
+                                let mut file = File::open(path)?;
+                                file.seek(SeekFrom::Start(i * chunk_size))?;
+                                hash_read(&mut file, DEFAULT_BUFFER_SIZE, chunk_size, on_block)
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    // combine the chunk hashes in order
+                    let mut hasher = Hasher::new();
+                    for handle in handles {
+                        hasher.combine(&handle.join().unwrap()?);
+                    }
+                    Ok(hasher.finalize())
+                })
+            }
+        }
+    }
+}
+
+impl Default for Backend {
+    #[cfg(feature = "mmap")]
+    fn default() -> Self {
+        Backend::Mmap
+    }
+
+    #[cfg(not(feature = "mmap"))]
+    fn default() -> Self {
+        Backend::Read(DEFAULT_BUFFER_SIZE)
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Backend::Read(buffer_size) => write!(f, "read:{}", buffer_size),
+            #[cfg(feature = "mmap")]
+            Backend::Mmap => write!(f, "mmap"),
+            Backend::Parallel(threads) => write!(f, "parallel:{}", threads),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let count = |n: &str| n.parse::<usize>().ok().filter(|&n| n > 0);
+        let backend = match s.trim().split_once(':') {
+            None if s.trim() == "read" => Some(Backend::Read(DEFAULT_BUFFER_SIZE)),
+            #[cfg(feature = "mmap")]
+            None if s.trim() == "mmap" => Some(Backend::Mmap),
+            Some(("read", n)) => count(n).map(Backend::Read),
+            Some(("parallel", n)) => count(n).map(Backend::Parallel),
+            _ => None,
+        };
+        backend.ok_or_else(|| format!("invalid backend: {:?}", s))
+    }
+}
+
+/// Hash at most `limit` bytes from a reader using a buffer of the given size.
+fn hash_read<R: Read>(
+    reader: &mut R,
+    buffer_size: usize,
+    limit: u64,
+    on_block: Option<&OnBlock<'_>>,
+) -> Result<Hasher, IoError> {
+    let mut hasher = Hasher::new();
+    let mut buffer = vec![0; buffer_size.max(1)];
+    let mut reader = reader.take(limit);
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        if let Some(on_block) = on_block {
+            on_block(n);
+        }
+    }
+    Ok(hasher)
+}
@@ -0,0 +1,130 @@
+use std::cmp::min;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::progress::format_size;
+use super::Backend;
+use super::Config;
+
+/// The size of the scratch file created when no file is given.
+const SCRATCH_SIZE: usize = 1 << 28;
+
+/// The number of times each backend is run, keeping the fastest run.
+const ROUNDS: usize = 3;
+
+/// A temporary file removed when dropped.
+struct ScratchFile(PathBuf);
+
+impl ScratchFile {
+    /// Create a new scratch file filled with `size` pseudo-random bytes.
+    fn create(size: usize) -> Result<Self, IoError> {
+        let path = std::env::temp_dir().join(format!("cksfv-benchmark-{}.bin", std::process::id()));
+        let scratch = ScratchFile(path);
+
+        // generate a block of data with a xorshift generator
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut block = Vec::with_capacity(1 << 20);
+        while block.len() < block.capacity() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            block.extend_from_slice(&state.to_le_bytes());
+        }
+
+        // write the block until the file has the requested size
+        let mut out = BufWriter::new(File::create(&scratch.0)?);
+        let mut written = 0;
+        while written < size {
+            let n = std::cmp::min(block.len(), size - written);
+            out.write_all(&block[..n])?;
+            written += n;
+        }
+        out.flush()?;
+
+        Ok(scratch)
+    }
+}
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Measure the throughput of every available backend and get the fastest.
+///
+/// Backends are run on `file`, or on a temporary scratch file if no file is
+/// given, and the results are written as a table to `config.stdout`. The
+/// bandwidth limit of the configuration is ignored.
+pub fn benchmark<C>(file: Option<&Path>, config: C) -> Result<Backend, IoError>
+where
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+
+    // get the file to hash
+    let scratch = match file {
+        Some(_) => None,
+        None => Some(ScratchFile::create(SCRATCH_SIZE)?),
+    };
+    let path = file.unwrap_or_else(|| &scratch.as_ref().unwrap().0);
+    let size = std::fs::metadata(path)?.len();
+
+    // read the file once so that every backend starts with the same cache
+    let expected = Backend::default().compute(path, None)?;
+
+    // run every backend and record its fastest time
+    writeln!(
+        cfg.stdout,
+        "--( Benchmark: {} ){}",
+        path.display(),
+        "-".repeat(63 - min(63, path.display().to_string().len()))
+    )?;
+    writeln!(
+        cfg.stdout,
+        "{:<20}{:>12}{:>16}",
+        "Backend", "Time", "Throughput"
+    )?;
+    let mut best: Option<(Backend, Duration)> = None;
+    for backend in Backend::candidates() {
+        let mut elapsed = Duration::MAX;
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            let crc32 = backend.compute(path, None)?;
+            elapsed = elapsed.min(start.elapsed());
+            if crc32 != expected {
+                let msg = format!("backend {} computed a wrong CRC", backend);
+                return Err(IoError::new(ErrorKind::InvalidData, msg));
+            }
+        }
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        writeln!(
+            cfg.stdout,
+            "{:<20}{:>10.3} s{:>14}/s",
+            backend.to_string(),
+            seconds,
+            format_size(size as f64 / seconds)
+        )?;
+        if best.map(|(_, t)| elapsed < t).unwrap_or(true) {
+            best = Some((backend, elapsed));
+        }
+    }
+
+    let (fastest, _) = best.unwrap();
+    writeln!(cfg.stdout, "{}", "-".repeat(80))?;
+    writeln!(
+        cfg.stdout,
+        "Fastest backend for {}: {}",
+        format_size(size as f64),
+        fastest
+    )?;
+    Ok(fastest)
+}
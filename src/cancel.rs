@@ -0,0 +1,32 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// A token to request the interruption of a running operation.
+///
+/// Clones of a token share the same state, so a token can be given to a
+/// [`Config`](crate::Config) and cancelled later from another thread, e.g.
+/// a signal handler. Cancelling a token does not abort the file currently
+/// being hashed: operations stop after it, and report the files checked
+/// so far.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the interruption of the operations using this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether the interruption was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::str::FromStr;
+
+use super::Output;
+
+/// When to color the text output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Color {
+    /// Color the output when writing to a terminal, unless `NO_COLOR` is set.
+    Auto,
+    /// Always color the output.
+    Always,
+    /// Never color the output.
+    #[default]
+    Never,
+}
+
+impl Color {
+    /// Check whether the output written to `out` should be colored.
+    pub(crate) fn enabled(&self, out: &Output) -> bool {
+        match self {
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").filter(|v| !v.is_empty());
+                no_color.is_none() && out.is_terminal()
+            }
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Color::Auto => write!(f, "auto"),
+            Color::Always => write!(f, "always"),
+            Color::Never => write!(f, "never"),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!("invalid color choice: {:?}", s)),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The style of a line of the text output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Style {
+    /// A file matching its entry.
+    Ok,
+    /// A file with a different CRC.
+    Mismatch,
+    /// A file that could not be read.
+    Error,
+}
+
+impl Style {
+    /// Get the ANSI escape sequence selecting the style.
+    fn code(&self) -> &'static str {
+        match self {
+            Style::Ok => "\x1b[32m",
+            Style::Mismatch => "\x1b[1;31m",
+            Style::Error => "\x1b[33m",
+        }
+    }
+
+    /// Wrap a line in the style if `enabled`, or leave it untouched.
+    pub fn paint(&self, line: String, enabled: bool) -> String {
+        if enabled {
+            format!("{}{}\x1b[0m", self.code(), line)
+        } else {
+            line
+        }
+    }
+}
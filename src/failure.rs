@@ -0,0 +1,68 @@
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// A class of failure, with the bit it sets in the exit code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// A file does not match its recorded CRC, or listings disagree.
+    Mismatch = 1,
+    /// A file or a listing does not exist.
+    Missing = 2,
+    /// A file or a listing could not be read or written.
+    Io = 4,
+    /// A listing contains an invalid entry.
+    Parse = 8,
+    /// A file is not covered by any listing in strict mode.
+    Unlisted = 16,
+}
+
+impl Failure {
+    /// Get the class of failure of an I/O error.
+    pub fn of(err: &IoError) -> Self {
+        match err.kind() {
+            ErrorKind::NotFound => Failure::Missing,
+            ErrorKind::InvalidData => Failure::Parse,
+            _ => Failure::Io,
+        }
+    }
+}
+
+/// The classes of failures encountered by operations.
+///
+/// Clones share the same state, so the failures recorded by the operations
+/// using a [`Config`](crate::Config) can be inspected once they returned,
+/// e.g. to choose the exit code of the program.
+#[derive(Clone, Debug, Default)]
+pub struct Failures {
+    flags: Arc<AtomicI32>,
+}
+
+impl Failures {
+    /// Create a new record without any failure.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a failure of the given class.
+    pub fn record(&self, failure: Failure) {
+        self.flags.fetch_or(failure as i32, Ordering::SeqCst);
+    }
+
+    /// Record the failure corresponding to an I/O error.
+    pub fn record_error(&self, err: &IoError) {
+        self.record(Failure::of(err))
+    }
+
+    /// Check whether a failure of the given class was recorded.
+    pub fn contains(&self, failure: Failure) -> bool {
+        self.flags.load(Ordering::SeqCst) & failure as i32 != 0
+    }
+
+    /// Get the bitwise combination of the failures recorded so far.
+    pub fn code(&self) -> i32 {
+        self.flags.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+
+/// A set of glob patterns selecting the files to process.
+///
+/// Patterns without a `/` are matched against the name of a file, and
+/// patterns with a `/` against its whole path. A file is selected if it
+/// matches at least one of the include patterns (or if there are none), and
+/// none of its parent directories match one of the exclude patterns.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl Filter {
+    /// Create a new filter from the given include and exclude patterns.
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Result<Self, String> {
+        Ok(Filter {
+            include: build_set(include)?,
+            exclude: build_set(exclude)?,
+        })
+    }
+
+    /// Check whether the file at the given relative path is selected.
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = strip_curdir(path.as_ref());
+        let included = match &self.include {
+            Some(set) => matches(set, &path),
+            None => true,
+        };
+        included && !self.is_excluded(&path)
+    }
+
+    /// Check whether the given relative path or one of its parents is excluded.
+    pub(crate) fn is_excluded<P: AsRef<Path>>(&self, path: P) -> bool {
+        match &self.exclude {
+            Some(set) => strip_curdir(path.as_ref())
+                .ancestors()
+                .filter(|p| !p.as_os_str().is_empty())
+                .any(|p| matches(set, p)),
+            None => false,
+        }
+    }
+}
+
+/// Build a glob set from a list of patterns, or `None` if there are none.
+fn build_set<S: AsRef<str>>(patterns: &[S]) -> Result<Option<GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.as_ref())
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("invalid pattern: {}", e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| format!("invalid pattern: {}", e))
+}
+
+/// Check whether a path or its file name matches one of the globs of a set.
+fn matches(set: &GlobSet, path: &Path) -> bool {
+    set.is_match(path) || path.file_name().map(|n| set.is_match(n)).unwrap_or(false)
+}
+
+/// Remove the `.` components of a path.
+fn strip_curdir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
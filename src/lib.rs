@@ -6,28 +6,70 @@ extern crate crc32fast;
 #[cfg(feature = "mmap")]
 extern crate memmap;
 
+mod backend;
+mod bench;
+mod cancel;
+mod color;
+mod failure;
+mod filter;
+mod limit;
+mod listing;
+mod progress;
+mod report;
+mod sched;
+mod sfvignore;
+mod stats;
+mod template;
+
+use std::cell::RefCell;
 use std::cmp::min;
+use std::collections::hash_map::Entry as HashMapEntry;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt::Debug;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
 use std::io::Error as IoError;
-use std::io::Read;
 use std::io::Write;
 use std::iter::IntoIterator;
+use std::path::Component;
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use chrono::DateTime;
 use chrono::Datelike;
 use chrono::Local;
 use chrono::Timelike;
-use crc32fast::Hasher;
 use getset::Getters;
 use getset::MutGetters;
 use getset::Setters;
 
-/// Use a 64k buffer size for better performance.
-const DEFAULT_BUFFER_SIZE: usize = 65536;
+pub use self::backend::Backend;
+pub use self::bench::benchmark;
+pub use self::cancel::CancellationToken;
+pub use self::color::Color;
+pub use self::failure::Failure;
+pub use self::failure::Failures;
+pub use self::filter::Filter;
+pub use self::limit::RateLimiter;
+pub use self::listing::Change;
+pub use self::listing::Entry;
+pub use self::listing::Line;
+pub use self::listing::Listing;
+pub use self::report::Format;
+pub use self::template::Template;
+
+use self::color::Style;
+use self::listing::path_key;
+use self::progress::Progress;
+use self::report::FileReport;
+use self::report::Operation;
+use self::report::Report;
+use self::report::Status;
+use self::sched::HashResults;
+use self::sfvignore::IgnoreFiles;
+use self::sfvignore::IGNORE_FILE;
+use self::stats::Stats;
 
 /// The final value of a CRC32 checksum round.
 pub type Crc32 = u32;
@@ -35,7 +77,10 @@ pub type Crc32 = u32;
 // ---------------------------------------------------------------------------
 
 /// Given a path to a file, attempt to compute its CRC32 hash.
-fn compute_crc32(file: &Path) -> Result<Crc32, IoError> {
+///
+/// The number of bytes read is reported to `progress` after each block,
+/// and throttled by the bandwidth limiter of `cfg` if any.
+fn compute_crc32(file: &Path, cfg: &Config, progress: Option<&Progress>) -> Result<Crc32, IoError> {
     // check the file is not a directory (File::open is fine opening
     // a directory and will just read it as an empty file, but we want
     // a hard error)
@@ -44,31 +89,22 @@ fn compute_crc32(file: &Path) -> Result<Crc32, IoError> {
     }
 
     // open the file and compute the hash
-    File::open(&file).and_then(compute_crc32_inner)
-}
-
-/// Compute a CRC32 from a file content using `mmap`.
-#[cfg(feature = "mmap")]
-fn compute_crc32_inner(mut file: File) -> Result<Crc32, IoError> {
-    let mut hasher = Hasher::new();
-    let mmap = unsafe { memmap::MmapOptions::new().map(&file)? };
-    hasher.update(&mmap[..]);
-    Ok(hasher.finalize())
-}
-
-/// Compute a CRC32 from a file content without using `mmap`.
-#[cfg(not(feature = "mmap"))]
-fn compute_crc32_inner(mut file: File) -> Result<Crc32, IoError> {
-    let mut hasher = Hasher::new();
-    let mut buffer = [0; DEFAULT_BUFFER_SIZE];
-    loop {
-        let n = file.read(&mut buffer)?;
-        if n == 0 {
-            break;
+    if let Some(progress) = progress {
+        progress.start_file(file);
+    }
+    let limiter = cfg.bwlimit.as_deref();
+    let on_block = |n| {
+        if let Some(progress) = progress {
+            progress.advance(n);
         }
-        hasher.update(&buffer[..n]);
+        if let Some(limiter) = limiter {
+            limiter.consume(n);
+        }
+    };
+    match (progress, limiter) {
+        (None, None) => cfg.backend.compute(file, None),
+        _ => cfg.backend.compute(file, Some(&on_block)),
     }
-    Ok(hasher.finalize())
 }
 
 // ---------------------------------------------------------------------------
@@ -82,6 +118,7 @@ pub enum Output {
     Devnull,
     Stdout(std::io::Stdout),
     Stderr(std::io::Stderr),
+    File(Arc<std::fs::File>),
 }
 
 impl Output {
@@ -96,6 +133,22 @@ impl Output {
     pub fn stderr() -> Self {
         Output::Stderr(std::io::stderr())
     }
+
+    /// Write to the given file, shared between all the clones of the output.
+    pub fn file(file: std::fs::File) -> Self {
+        Output::File(Arc::new(file))
+    }
+
+    /// Check whether the output is written to a terminal.
+    pub fn is_terminal(&self) -> bool {
+        use std::io::IsTerminal;
+        match self {
+            Output::Devnull => false,
+            Output::Stdout(out) => out.is_terminal(),
+            Output::Stderr(err) => err.is_terminal(),
+            Output::File(file) => file.is_terminal(),
+        }
+    }
 }
 
 impl Clone for Output {
@@ -105,6 +158,7 @@ impl Clone for Output {
             Devnull => Self::devnull(),
             Stdout(_) => Self::stdout(),
             Stderr(_) => Self::stderr(),
+            File(file) => File(file.clone()),
         }
     }
 }
@@ -122,6 +176,7 @@ impl Write for Output {
             Devnull => Ok(buf.len()),
             Stdout(out) => out.write(buf),
             Stderr(err) => err.write(buf),
+            File(file) => file.as_ref().write(buf),
         }
     }
 
@@ -131,6 +186,7 @@ impl Write for Output {
             Devnull => Ok(()),
             Stdout(out) => out.flush(),
             Stderr(err) => err.flush(),
+            File(file) => file.as_ref().flush(),
         }
     }
 }
@@ -163,6 +219,86 @@ pub struct Config {
     #[get_mut = "pub"]
     #[set = "pub"]
     force_slashes: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    bwlimit: Option<Arc<RateLimiter>>,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    jobs: usize,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    follow_links: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    progress: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    cancellation: CancellationToken,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    failures: Failures,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    backend: Backend,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    recursive: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    orphans: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    strict: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    renames: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    porcelain: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    rebase: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    filter: Filter,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    locate: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    rewrite: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    format: Format,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    color: Color,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    stats: bool,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    #[set = "pub"]
+    template: Option<Template>,
 }
 
 impl Default for Config {
@@ -181,6 +317,26 @@ impl Config {
             print_basename: false,
             ignore_case: false,
             force_slashes: false,
+            bwlimit: None,
+            jobs: 1,
+            follow_links: false,
+            progress: false,
+            cancellation: CancellationToken::new(),
+            failures: Failures::new(),
+            backend: Backend::default(),
+            recursive: false,
+            orphans: false,
+            strict: false,
+            renames: false,
+            porcelain: false,
+            rebase: false,
+            filter: Filter::default(),
+            locate: false,
+            rewrite: false,
+            format: Format::default(),
+            color: Color::default(),
+            stats: false,
+            template: None,
         }
     }
 
@@ -199,6 +355,56 @@ impl Config {
         self
     }
 
+    pub fn with_bwlimit(mut self, bwlimit: Arc<RateLimiter>) -> Self {
+        self.bwlimit = Some(bwlimit);
+        self
+    }
+
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn with_failures(mut self, failures: Failures) -> Self {
+        self.failures = failures;
+        self
+    }
+
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn with_template(mut self, template: Template) -> Self {
+        self.template = Some(template);
+        self
+    }
+
     /// Consume the configuration instance and get the `stdout` field.
     pub fn extract_stdout(self) -> Output {
         self.stdout
@@ -207,10 +413,192 @@ impl Config {
 
 // ---------------------------------------------------------------------------
 
+/// Check whether the given path looks like a SFV listing.
+fn is_sfv(path: &Path) -> bool {
+    path.extension().map(|x| x == "sfv").unwrap_or(false)
+}
+
+/// Check whether a directory entry found under `root` passes the filter.
+///
+/// Directories are only rejected if they are excluded, so that the files
+/// they contain can still be selected by an include pattern.
+fn is_selected(root: &Path, entry: &walkdir::DirEntry, filter: &Filter) -> bool {
+    let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+    if entry.depth() == 0 {
+        true
+    } else if entry.file_type().is_dir() {
+        !filter.is_excluded(path)
+    } else {
+        filter.is_match(path)
+    }
+}
+
+/// Check whether a directory entry is ignored by a `.sfvignore` file.
+fn is_ignored(entry: &walkdir::DirEntry, ignores: &mut IgnoreFiles) -> bool {
+    entry.depth() > 0 && ignores.is_ignored(entry.path(), entry.file_type().is_dir())
+}
+
+/// Write the errors found in the `.sfvignore` files to `config.stderr`.
+fn report_ignore_errors(mut ignores: IgnoreFiles, cfg: &mut Config) -> Result<(), IoError> {
+    for (path, err) in ignores.take_errors() {
+        writeln!(cfg.stderr, "cksfv: {}: {}", path.display(), err)?;
+    }
+    Ok(())
+}
+
+/// Get the comment lines identifying the program that generated a listing.
+fn generator_header() -> Vec<String> {
+    let now: DateTime<Local> = Local::now();
+    vec![
+        format!(
+            "; Generated by cksfv.rs v{} on {:04}-{:02}-{:02} at {:02}:{:02}.{:02}",
+            crate_version!(),
+            now.year(),
+            now.month(),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second(),
+        ),
+        format!("; Project web site: {}", env!("CARGO_PKG_REPOSITORY")),
+        String::from(";"),
+    ]
+}
+
+/// Collect the files found under `root`, down to `max_depth`.
+///
+/// SFV listings and `.sfvignore` files found while traversing directories
+/// are skipped, as well as the files ignored by a `.sfvignore` file or not
+/// selected by the filter of `config`. Errors are written to
+/// `config.stderr`, and make `success` false.
+fn walk_files(
+    root: &Path,
+    max_depth: usize,
+    cfg: &mut Config,
+    success: &mut bool,
+) -> Result<Vec<PathBuf>, IoError> {
+    let mut files = Vec::new();
+    let filter = cfg.filter.clone();
+    let ignores = RefCell::new(IgnoreFiles::new(root));
+    let it = walkdir::WalkDir::new(root)
+        .max_depth(max_depth)
+        .follow_links(cfg.follow_links)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            is_selected(root, entry, &filter) && !is_ignored(entry, &mut ignores.borrow_mut())
+        });
+    for entry in it {
+        match entry {
+            Ok(entry) if entry.file_type().is_dir() => (),
+            Ok(entry) if entry.depth() > 0 && is_sfv(entry.path()) => (),
+            Ok(entry) if entry.depth() > 0 && entry.file_name() == IGNORE_FILE => (),
+            Ok(entry) => files.push(entry.into_path()),
+            Err(err) => {
+                *success = false;
+                cfg.failures.record(Failure::Io);
+                let path = err.path().unwrap_or(root).to_path_buf();
+                match err.into_io_error() {
+                    Some(e) => writeln!(cfg.stderr, "cksfv: {}: {}", path.display(), e)?,
+                    None => writeln!(cfg.stderr, "cksfv: {}: File system loop", path.display())?,
+                }
+            }
+        }
+    }
+    report_ignore_errors(ignores.into_inner(), cfg)?;
+    Ok(files)
+}
+
+/// Get the header comment line describing a file, listed under `name`.
+fn file_header(file: &Path, name: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(file).ok()?;
+    let mtime: DateTime<Local> = From::from(metadata.modified().ok()?);
+    Some(format!(
+        "; {:>12}  {:02}:{:02}.{:02} {:04}-{:02}-{:02} {}",
+        metadata.len(),
+        mtime.hour(),
+        mtime.minute(),
+        mtime.second(),
+        mtime.year(),
+        mtime.month(),
+        mtime.day(),
+        name.display()
+    ))
+}
+
+/// Get the name a file is listed under, honoring `config.print_basename`.
+fn listed_name<'a>(file: &'a Path, cfg: &Config) -> &'a Path {
+    match file.file_name() {
+        Some(name) if cfg.print_basename => Path::new(name),
+        _ => file,
+    }
+}
+
+/// Write a SFV listing for the given files, whose hashes are being computed.
+///
+/// `results` must yield the hashing results of `files` in the same order.
+/// If a `template` is given, each file is written with the template instead,
+/// without any header.
+fn write_listing<W, R>(
+    out: &mut W,
+    files: &[PathBuf],
+    results: R,
+    template: Option<&Template>,
+    cfg: &mut Config,
+) -> Result<bool, IoError>
+where
+    W: Write,
+    R: Iterator<Item = Result<Crc32, IoError>>,
+{
+    // generate the headers from the files that where found
+    if template.is_none() {
+        for line in generator_header() {
+            writeln!(out, "{}", line)?;
+        }
+        for file in files.iter().filter(|p| p.is_file()) {
+            if let Some(header) = file_header(file, listed_name(file, cfg)) {
+                writeln!(out, "{}", header)?;
+            }
+        }
+    }
+
+    // compute CRC32 of each file and generate the SFV listing
+    let mut success = true;
+    let mut done = 0;
+    for (file, result) in files.iter().zip(results) {
+        done += 1;
+        let name = listed_name(file, cfg);
+        match result {
+            Ok(crc32) => match template {
+                Some(template) => {
+                    let record = FileReport::new(None, name, file, None, &Ok(crc32));
+                    writeln!(out, "{}", template.render(&record, file))?
+                }
+                None => writeln!(out, "{} {:08X}", name.display(), crc32)?,
+            },
+            Err(err) => {
+                success = false;
+                cfg.failures.record_error(&err);
+                writeln!(cfg.stderr, "cksfv: {}: {}", file.display(), err)?
+            }
+        }
+    }
+
+    // report if the generation was interrupted before the end
+    if done < files.len() && cfg.cancellation.is_cancelled() {
+        success = false;
+        writeln!(cfg.stderr, "cksfv: Interrupted")?;
+    }
+
+    // return `true` if all CRC32 where successfully computed
+    Ok(success)
+}
+
 /// Generate a new SFV listing from a list of files.
 ///
 /// This function always writes the result to `config.stdout`, which defaults
-/// to `std::io::Stdout` if no configuration is provided.
+/// to `std::io::Stdout` if no configuration is provided. In recursive mode,
+/// directories are traversed and all the files they contain are listed.
 pub fn newsfv<'a, F, C>(files: F, config: C) -> Result<bool, IoError>
 where
     F: IntoIterator<Item = &'a Path>,
@@ -219,157 +607,1053 @@ where
     // get a default config if none provided.
     let mut cfg: Config = config.into().unwrap_or_default();
 
-    // collect the files
-    let files: Vec<&Path> = files.into_iter().collect();
+    // collect the files, traversing directories in recursive mode
+    let mut success = true;
+    let mut paths = Vec::new();
+    for file in files {
+        if cfg.recursive {
+            paths.extend(walk_files(file, usize::MAX, &mut cfg, &mut success)?);
+        } else {
+            paths.push(file.to_path_buf());
+        }
+    }
 
-    // generate the headers from the files that where found
-    let now: DateTime<Local> = Local::now();
-    writeln!(
-        cfg.stdout,
-        "; Generated by cksfv.rs v{} on {:04}-{:02}-{:02} at {:02}:{:02}.{:02}",
-        crate_version!(),
-        now.year(),
-        now.month(),
-        now.day(),
-        now.hour(),
-        now.minute(),
-        now.second(),
-    )?;
-    writeln!(
-        cfg.stdout,
-        "; Project web site: {}",
-        env!("CARGO_PKG_REPOSITORY")
-    )?;
-    writeln!(cfg.stdout, ";")?;
-    for file in files.iter().filter(|p| p.is_file()) {
-        if let Ok(metadata) = std::fs::metadata(file) {
-            let mtime: DateTime<Local> = From::from(metadata.modified().unwrap());
-            writeln!(
-                cfg.stdout,
-                "; {:>12}  {:02}:{:02}.{:02} {:04}-{:02}-{:02} {}",
-                metadata.len(),
-                mtime.hour(),
-                mtime.minute(),
-                mtime.second(),
-                mtime.year(),
-                mtime.month(),
-                mtime.day(),
-                file.display()
-            )?;
+    // generate the listing while the files are being hashed
+    let mut results = sched::hash_files(paths.iter().cloned(), &cfg);
+    let mut stdout = cfg.stdout.clone();
+    match cfg.format.report(Operation::Create, stdout.clone())? {
+        None => {
+            let template = cfg.template.clone();
+            success &= write_listing(&mut stdout, &paths, results, template.as_ref(), &mut cfg)?
+        }
+        Some(mut report) => {
+            let mut done = 0;
+            for file in paths.iter() {
+                let name = listed_name(file, &cfg);
+                report.start_file(None, name)?;
+                let result = match results.next() {
+                    Some(result) => result,
+                    None => break,
+                };
+                done += 1;
+                if let Err(err) = &result {
+                    success = false;
+                    cfg.failures.record_error(err);
+                }
+                let record = FileReport::new(None, name, file, None, &result);
+                report.record(record.with_elapsed(results.elapsed()))?;
+            }
+            let interrupted = done < paths.len() && cfg.cancellation.is_cancelled();
+            success &= !interrupted;
+            report.finish(interrupted)?;
         }
     }
+    Ok(success)
+}
 
-    // compute CRC32 of each file and generate the SFV listing
+/// Generate a new SFV listing in each directory found under the given roots.
+///
+/// Every directory containing at least one file gets a listing named `name`
+/// with the basenames of its files, as if generated with the `-b` flag from
+/// within that directory. Existing SFV listings are not listed, and listings
+/// are only written once all of their files were hashed.
+pub fn newsfv_per_directory<'a, F, C>(roots: F, name: &str, config: C) -> Result<bool, IoError>
+where
+    F: IntoIterator<Item = &'a Path>,
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+    cfg.print_basename = true;
+
+    // collect the files and group them by directory
     let mut success = true;
-    for file in &files {
-        match compute_crc32(file) {
-            Ok(crc32) if cfg.print_basename => {
-                let name = file.file_name().unwrap();
-                writeln!(
-                    cfg.stdout,
-                    "{} {:08X}",
-                    AsRef::<Path>::as_ref(&name).display(),
-                    crc32
-                )?
+    let mut dirs: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for root in roots {
+        for file in walk_files(root, usize::MAX, &mut cfg, &mut success)? {
+            let dir = file.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+            dirs.entry(dir).or_default().push(file);
+        }
+    }
+
+    // start hashing the files of every directory
+    let mut results = sched::hash_files(dirs.values().flatten().cloned(), &cfg);
+
+    // write the listing of each directory in order
+    for (dir, files) in dirs {
+        let mut listing = Vec::new();
+        let results = results.by_ref().take(files.len());
+        success &= write_listing(&mut listing, &files, results, None, &mut cfg)?;
+        if cfg.cancellation.is_cancelled() {
+            success = false;
+            break;
+        }
+        let path = dir.join(name);
+        if let Err(err) = std::fs::write(&path, listing) {
+            success = false;
+            cfg.failures.record_error(&err);
+            writeln!(cfg.stderr, "cksfv: {}: {}", path.display(), err)?;
+        }
+    }
+
+    Ok(success)
+}
+
+/// Add new files to an existing SFV listing, without rehashing listed files.
+///
+/// Only the files without an entry in the listing are hashed. Their header
+/// lines are inserted after the existing header, their entries after the
+/// existing entries, sorted by path, and the listing is then replaced. The
+/// new entries are also written to `config.stdout`.
+///
+/// If `files` is `None`, the directory containing the listing is scanned for
+/// files instead (recursively in recursive mode). In both cases, the files
+/// are listed relative to the directory containing the listing.
+pub fn update<'a, F, C>(sfv: &Path, files: Option<F>, config: C) -> Result<bool, IoError>
+where
+    F: IntoIterator<Item = &'a Path>,
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+
+    // read the existing listing
+    let mut listing = match Listing::read(sfv) {
+        Ok(listing) => listing,
+        Err(err) => {
+            cfg.failures.record_error(&err);
+            writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+            return Ok(false);
+        }
+    };
+
+    // collect the candidate files with the name they would be listed under
+    let mut success = true;
+    let mut candidates = Vec::new();
+    let depth = if cfg.recursive { usize::MAX } else { 1 };
+    let workdir = sfv.parent().filter(|p| !p.as_os_str().is_empty());
+    let workdir = workdir.unwrap_or_else(|| Path::new("."));
+    if let Some(files) = files {
+        for file in files {
+            let paths = if cfg.recursive {
+                walk_files(file, depth, &mut cfg, &mut success)?
+            } else {
+                vec![file.to_path_buf()]
+            };
+            for path in paths {
+                let name = match path.file_name() {
+                    Some(name) if cfg.print_basename => PathBuf::from(name),
+                    _ => relative_path(&path, workdir).unwrap_or_else(|| path.clone()),
+                };
+                candidates.push((path, name));
+            }
+        }
+    } else {
+        for path in walk_files(workdir, depth, &mut cfg, &mut success)? {
+            let name = path.strip_prefix(workdir).unwrap().to_path_buf();
+            candidates.push((path, name));
+        }
+    }
+
+    // only keep the files that are not listed yet
+    candidates.retain(|(path, name)| !is_sfv(path) && !listing.contains(name, cfg.ignore_case));
+    candidates.sort_by(|a, b| a.1.cmp(&b.1));
+    candidates.dedup_by(|a, b| a.1 == b.1);
+    if candidates.is_empty() {
+        return Ok(success);
+    }
+
+    // hash the new files
+    let mut headers = Vec::new();
+    let mut entries = Vec::new();
+    let results = sched::hash_files(candidates.iter().map(|(path, _)| path.clone()), &cfg);
+    for ((path, name), result) in candidates.iter().zip(results) {
+        match result {
+            Ok(crc32) => {
+                writeln!(cfg.stdout, "{} {:08X}", name.display(), crc32)?;
+                headers.extend(file_header(path, name).map(Line::Comment));
+                entries.push(Line::Entry(Entry::new(name.clone(), crc32)));
             }
-            Ok(crc32) => writeln!(cfg.stdout, "{} {:08X}", file.display(), crc32)?,
             Err(err) => {
                 success = false;
-                writeln!(cfg.stderr, "cksfv: {}: {}", file.display(), err)?
+                cfg.failures.record_error(&err);
+                writeln!(cfg.stderr, "cksfv: {}: {}", path.display(), err)?;
             }
         }
     }
+    if entries.len() < candidates.len() && cfg.cancellation.is_cancelled() {
+        success = false;
+        writeln!(cfg.stderr, "cksfv: Interrupted")?;
+    }
 
-    // return `true` if all CRC32 where successfully computed
+    // insert the new entries after the last entry, and the new headers
+    // before the first entry
+    let lines = listing.lines_mut();
+    let is_entry = |line: &Line| matches!(line, Line::Entry(_));
+    let last = lines.iter().rposition(is_entry).map(|i| i + 1);
+    let at = last.unwrap_or(lines.len());
+    lines.splice(at..at, entries);
+    let at = lines.iter().position(is_entry).unwrap_or(lines.len());
+    lines.splice(at..at, headers);
+
+    // replace the listing
+    if let Err(err) = listing.save(sfv) {
+        cfg.failures.record_error(&err);
+        writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+        return Ok(false);
+    }
     Ok(success)
 }
 
-/// Check a SFV listing at the given location, optionally using `workdir`.
+/// Remove the entries of a SFV listing whose files no longer exist.
 ///
-/// This function always writes some progress messages to `config.stderr`, and
-/// outputs a message line for each file it checks to `config.stdout`.
-pub fn cksfv<'a, F, C>(
+/// Entries are resolved relative to the directory containing the listing.
+/// Comments and header lines are kept, as are entries whose files exist
+/// but cannot be read or have a different CRC. The removed entries are
+/// written to `config.stdout`, and the listing is only replaced if any
+/// entry was removed.
+pub fn prune<C>(sfv: &Path, config: C) -> Result<bool, IoError>
+where
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+
+    // read the existing listing
+    let mut listing = match Listing::read(sfv) {
+        Ok(listing) => listing,
+        Err(err) => {
+            cfg.failures.record_error(&err);
+            writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+            return Ok(false);
+        }
+    };
+
+    // remove the entries whose file is missing
+    let workdir = sfv.parent().unwrap_or_else(|| Path::new(""));
+    let mut removed = Vec::new();
+    listing.lines_mut().retain(|line| match line {
+        Line::Entry(entry) => match std::fs::metadata(workdir.join(entry.path())) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                removed.push(entry.clone());
+                false
+            }
+            _ => true,
+        },
+        Line::Comment(_) => true,
+    });
+    if removed.is_empty() {
+        return Ok(true);
+    }
+    for entry in &removed {
+        writeln!(
+            cfg.stdout,
+            "{} {:08X}",
+            entry.path().display(),
+            entry.crc32()
+        )?;
+    }
+
+    // replace the listing
+    if let Err(err) = listing.save(sfv) {
+        cfg.failures.record_error(&err);
+        writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Record the current CRC of the files whose entry in a SFV listing differs.
+///
+/// Entries are resolved relative to the directory containing the listing,
+/// and `confirm` is called with each mismatched entry and the new CRC to
+/// decide whether the entry should be updated. The updated entries are
+/// written to `config.stdout`, and the listing is then written to `output`,
+/// or replaced atomically if `output` is `None` and any entry was updated.
+/// Files that cannot be read are reported to `config.stderr` and their
+/// entries are left unchanged.
+pub fn fix<F, C>(
     sfv: &Path,
-    workdir: Option<&Path>,
+    output: Option<&Path>,
+    mut confirm: F,
     config: C,
-    files: Option<F>,
 ) -> Result<bool, IoError>
 where
-    F: IntoIterator<Item = &'a Path>,
+    F: FnMut(&Entry, Crc32) -> bool,
     C: Into<Option<Config>>,
 {
     // get a default config if none provided.
     let mut cfg: Config = config.into().unwrap_or_default();
 
-    // print the terminal "UI"
-    let workdir = workdir.unwrap_or_else(|| Path::new("."));
-    writeln!(
-        cfg.stderr,
-        "--( Verifying: {} ){}",
-        sfv.display(),
-        "-".repeat(63 - min(63, sfv.display().to_string().len()))
-    )?;
-
-    // open the SFV listing
-    let listing = match File::open(sfv) {
-        Ok(file) => BufReader::new(file),
+    // read the existing listing
+    let mut listing = match Listing::read(sfv) {
+        Ok(listing) => listing,
         Err(err) => {
+            cfg.failures.record_error(&err);
             writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
             return Ok(false);
         }
     };
 
+    // hash the listed files and update the mismatched entries
     let mut success = true;
-    let mut lines = listing.lines();
-    if let Some(_files) = files {
-        // only check the files given as arguments
-        unimplemented!("TODO: checking with file arguments");
-    } else {
-        // check every line of the listing
-        while let Some(Ok(line)) = lines.next() {
-            if !line.starts_with(';') {
-                // extract filename and CRC from listing
-                let i = line.trim_end().rfind(' ').unwrap();
-                let filename = Path::new(&line[..i]);
-                let crc32_old = u32::from_str_radix(&line[i + 1..], 16).unwrap();
-                // check the current CRC32 and compare against recorded one
-                match compute_crc32(&workdir.join(filename)) {
-                    Ok(crc32_new) if crc32_new != crc32_old => {
-                        success = false;
-                        if cfg.quiet {
-                            writeln!(cfg.stdout, "{:<50}different CRC", filename.display())?;
-                        } else {
-                            writeln!(
-                                cfg.stdout,
-                                "cksfv: {}: Has a different CRC",
-                                filename.display()
-                            )?;
-                        }
-                    }
-                    Err(err) if cfg.quiet => {
-                        writeln!(cfg.stdout, "cksfv: {}: {}", filename.display(), err)?;
-                    }
-                    Err(err) => {
-                        writeln!(cfg.stdout, "{:<50}{:<30}", filename.display(), err)?;
-                        success = false
-                    }
-                    Ok(_) if !cfg.quiet => {
-                        writeln!(cfg.stdout, "{:<50}OK", filename.display())?;
-                    }
-                    Ok(_) => (),
+    let mut checked = 0;
+    let mut fixed = 0;
+    let workdir = sfv.parent().unwrap_or_else(|| Path::new(""));
+    let files = listing
+        .entries()
+        .map(|e| workdir.join(e.path()))
+        .collect::<Vec<_>>();
+    let results = sched::hash_files(files, &cfg);
+    let entries = listing
+        .lines_mut()
+        .iter_mut()
+        .filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            Line::Comment(_) => None,
+        });
+    for (entry, result) in entries.zip(results) {
+        checked += 1;
+        match result {
+            Ok(crc32) if crc32 != entry.crc32() && confirm(entry, crc32) => {
+                *entry = Entry::new(entry.path().clone(), crc32);
+                fixed += 1;
+                writeln!(cfg.stdout, "{} {:08X}", entry.path().display(), crc32)?;
+            }
+            Ok(_) => (),
+            Err(err) => {
+                success = false;
+                cfg.failures.record_error(&err);
+                writeln!(cfg.stderr, "cksfv: {}: {}", entry.path().display(), err)?;
+            }
+        }
+    }
+
+    // leave the listing untouched if interrupted
+    if checked < listing.entries().count() && cfg.cancellation.is_cancelled() {
+        writeln!(cfg.stderr, "cksfv: Interrupted")?;
+        return Ok(false);
+    }
+
+    // write the new listing, unless it would replace an unchanged listing
+    if fixed == 0 && output.is_none() {
+        return Ok(success);
+    }
+    let dst = output.unwrap_or(sfv);
+    if let Err(err) = listing.save(dst) {
+        cfg.failures.record_error(&err);
+        writeln!(cfg.stderr, "cksfv: {}: {}", dst.display(), err)?;
+        return Ok(false);
+    }
+    Ok(success)
+}
+
+/// Report the differences between two SFV listings.
+///
+/// Added, removed and changed entries are written to `config.stdout`, one
+/// per line, either in a human-readable format or, in porcelain mode, as
+/// tab-separated fields starting with a status letter (`A`, `D`, `M` or
+/// `R`). Renames are only detected if `config.renames` is set. Returns
+/// `true` if the listings have the same entries.
+pub fn diff<C>(old: &Path, new: &Path, config: C) -> Result<bool, IoError>
+where
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+
+    // read both listings
+    let mut listings = Vec::with_capacity(2);
+    for sfv in [old, new] {
+        match Listing::read(sfv) {
+            Ok(listing) => listings.push(listing),
+            Err(err) => {
+                cfg.failures.record_error(&err);
+                writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+                return Ok(false);
+            }
+        }
+    }
+
+    // report the changes
+    let changes = listings[0].diff(&listings[1], cfg.renames, cfg.ignore_case);
+    for change in &changes {
+        match (change, cfg.porcelain) {
+            (Change::Added(e), false) => writeln!(
+                cfg.stdout,
+                "added    {} {:08X}",
+                e.path().display(),
+                e.crc32()
+            )?,
+            (Change::Added(e), true) => {
+                writeln!(cfg.stdout, "A\t{}\t{:08X}", e.path().display(), e.crc32())?
+            }
+            (Change::Removed(e), false) => writeln!(
+                cfg.stdout,
+                "removed  {} {:08X}",
+                e.path().display(),
+                e.crc32()
+            )?,
+            (Change::Removed(e), true) => {
+                writeln!(cfg.stdout, "D\t{}\t{:08X}", e.path().display(), e.crc32())?
+            }
+            (Change::Changed { old, new }, false) => writeln!(
+                cfg.stdout,
+                "changed  {} {:08X} -> {:08X}",
+                old.path().display(),
+                old.crc32(),
+                new.crc32()
+            )?,
+            (Change::Changed { old, new }, true) => writeln!(
+                cfg.stdout,
+                "M\t{}\t{:08X}\t{:08X}",
+                old.path().display(),
+                old.crc32(),
+                new.crc32()
+            )?,
+            (Change::Renamed { old, new }, false) => writeln!(
+                cfg.stdout,
+                "renamed  {} -> {} {:08X}",
+                old.path().display(),
+                new.path().display(),
+                new.crc32()
+            )?,
+            (Change::Renamed { old, new }, true) => writeln!(
+                cfg.stdout,
+                "R\t{}\t{}\t{:08X}",
+                old.path().display(),
+                new.path().display(),
+                new.crc32()
+            )?,
+        }
+    }
+
+    Ok(changes.is_empty())
+}
+
+/// Merge several SFV listings into a single listing.
+///
+/// Identical entries are only written once, and entries listing the same
+/// file with different CRCs are reported to `config.stderr`, keeping the
+/// entry of the first listing. If `config.rebase` is set, entries are made
+/// relative to the directory of `output` (or the current directory) instead
+/// of the directory of their listing, going through `..` components if
+/// needed. The merged listing is written to
+/// `output`, or to `config.stdout` if `output` is `None`.
+pub fn merge<'a, I, C>(listings: I, output: Option<&Path>, config: C) -> Result<bool, IoError>
+where
+    I: IntoIterator<Item = &'a Path>,
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+
+    // the directory the merged entries are relative to
+    let base = output
+        .and_then(Path::parent)
+        .unwrap_or_else(|| Path::new(""));
+
+    // collect the entries of every listing
+    let mut success = true;
+    let mut merged = Listing::new();
+    let mut seen = HashMap::new();
+    merged
+        .lines_mut()
+        .extend(generator_header().into_iter().map(Line::Comment));
+    for sfv in listings {
+        let listing = match Listing::read(sfv) {
+            Ok(listing) => listing,
+            Err(err) => {
+                cfg.failures.record_error(&err);
+                writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+                success = false;
+                continue;
+            }
+        };
+        let workdir = sfv.parent().unwrap_or_else(|| Path::new(""));
+        for entry in listing.entries() {
+            let path = if cfg.rebase {
+                let path = workdir.join(entry.path());
+                relative_path(&path, base).unwrap_or_else(|| normalize_path(&path))
+            } else {
+                entry.path().clone()
+            };
+            match seen.entry(path_key(&path, cfg.ignore_case)) {
+                HashMapEntry::Vacant(v) => {
+                    v.insert((entry.crc32(), sfv));
+                    merged
+                        .lines_mut()
+                        .push(Line::Entry(Entry::new(path, entry.crc32())));
+                }
+                HashMapEntry::Occupied(o) if o.get().0 != entry.crc32() => {
+                    let (crc32, first) = o.get();
+                    writeln!(
+                        cfg.stderr,
+                        "cksfv: {}: conflicting CRC {:08X} in {} and {:08X} in {}",
+                        path.display(),
+                        crc32,
+                        first.display(),
+                        entry.crc32(),
+                        sfv.display()
+                    )?;
+                    cfg.failures.record(Failure::Mismatch);
+                    success = false;
+                }
+                HashMapEntry::Occupied(_) => (),
+            }
+        }
+    }
+
+    // write the merged listing
+    let result = match output {
+        Some(path) => merged.save(path),
+        None => merged.write(&mut cfg.stdout),
+    };
+    if let Err(err) = result {
+        cfg.failures.record_error(&err);
+        let name = output.unwrap_or_else(|| Path::new("<stdout>"));
+        writeln!(cfg.stderr, "cksfv: {}: {}", name.display(), err)?;
+        return Ok(false);
+    }
+    Ok(success)
+}
+
+/// Read the entries of a SFV listing selected by the given filter.
+///
+/// Entries are matched against the filter with their path prefixed by
+/// `prefix`, the directory of the listing relative to the walk root.
+fn read_listing(sfv: &Path, prefix: &Path, filter: &Filter) -> Result<Vec<Entry>, IoError> {
+    Listing::read(sfv).map(|listing| {
+        listing
+            .entries()
+            .filter(|e| filter.is_match(prefix.join(e.path())))
+            .cloned()
+            .collect()
+    })
+}
+
+/// Remove the `.` components of a path, and resolve its `..` components
+/// without following links.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Get the path leading to `path` from the directory `base`.
+///
+/// Relative paths are resolved against the current directory first, so the
+/// result may start with `..` components. Returns `None` if no such path
+/// exists, e.g. for paths on different drives.
+fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    let path = normalize_path(&cwd.join(path));
+    let base = normalize_path(&cwd.join(base));
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return None;
+    }
+    let mut relative = PathBuf::new();
+    for component in base.components().skip(common) {
+        match component {
+            Component::Normal(_) => relative.push(".."),
+            _ => return None,
+        }
+    }
+    relative.extend(path.components().skip(common));
+    Some(relative)
+}
+
+/// Report the files found under `root` whose key is not in `listed`.
+///
+/// Unlisted files are only considered errors in strict mode, in which case
+/// `false` is returned if any was found. They are recorded in `report` if
+/// given, instead of being written to `config.stdout`.
+fn report_orphans(
+    root: &Path,
+    max_depth: usize,
+    listed: &HashSet<String>,
+    mut report: Option<&mut Report>,
+    cfg: &mut Config,
+) -> Result<bool, IoError> {
+    let mut walked = true;
+    let mut success = true;
+    for file in walk_files(root, max_depth, cfg, &mut walked)? {
+        if !listed.contains(&path_key(&file, cfg.ignore_case)) {
+            let name = file.strip_prefix(root).unwrap_or(&file);
+            if let Some(report) = report.as_deref_mut() {
+                report.record(FileReport::unlisted(name, &file))?;
+            } else if !cfg.quiet || cfg.strict {
+                let line = match &cfg.template {
+                    Some(template) => template.render(&FileReport::unlisted(name, &file), &file),
+                    None if cfg.quiet => format!("cksfv: {}: Not listed", name.display()),
+                    None => format!("{:<50}Not listed", name.display()),
+                };
+                writeln!(cfg.stdout, "{}", line)?;
+            }
+            if cfg.strict {
+                cfg.failures.record(Failure::Unlisted);
+            }
+            success = false;
+        }
+    }
+    Ok((success && walked) || !cfg.strict)
+}
+
+/// Get the sizes recorded in the header lines of a SFV listing.
+fn header_sizes(listing: &Listing) -> HashMap<PathBuf, u64> {
+    let mut sizes = HashMap::new();
+    for line in listing.lines() {
+        // header lines look like `; <size>  <hh:mm.ss> <yyyy-mm-dd> <name>`
+        if let Line::Comment(comment) = line {
+            let mut fields = Vec::with_capacity(3);
+            let mut rest = comment.trim_start_matches(';').trim_start();
+            while let (true, Some((field, tail))) = (fields.len() < 3, rest.split_once(' ')) {
+                fields.push(field);
+                rest = tail.trim_start();
+            }
+            if let [size, _, _] = fields[..] {
+                if let Ok(size) = size.parse::<u64>() {
+                    sizes.insert(PathBuf::from(rest), size);
                 }
             }
         }
     }
+    sizes
+}
+
+/// Look for the files of missing entries among the unlisted files.
+///
+/// Files found under `workdir` whose key is not in `listed` are hashed,
+/// and matched to the missing entries with the same CRC, and the same size
+/// if the listing header records one. Matches are reported to
+/// `config.stdout` if `verbose` is true, and written to the listing at `sfv`
+/// if enabled. The new name of each missing entry is returned, if found.
+fn locate_missing(
+    sfv: &Path,
+    workdir: &Path,
+    missing: &[&Entry],
+    listed: &HashSet<String>,
+    verbose: bool,
+    cfg: &mut Config,
+) -> Result<Vec<Option<PathBuf>>, IoError> {
+    // get the sizes of the missing files, if known
+    let mut listing = match Listing::read(sfv) {
+        Ok(listing) => listing,
+        Err(err) => {
+            writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+            return Ok(vec![None; missing.len()]);
+        }
+    };
+    let sizes = header_sizes(&listing);
+    let sizes = missing
+        .iter()
+        .map(|e| sizes.get(e.path()).cloned())
+        .collect::<Vec<_>>();
+
+    // collect the unlisted files which could be a missing file
+    let mut walked = true;
+    let candidates = walk_files(workdir, usize::MAX, cfg, &mut walked)?
+        .into_iter()
+        .filter(|path| !listed.contains(&path_key(path, cfg.ignore_case)))
+        .map(|path| (std::fs::metadata(&path).map(|m| m.len()).ok(), path))
+        .filter(|(len, _)| sizes.iter().any(|size| size.is_none() || *size == *len))
+        .collect::<Vec<_>>();
+
+    // match the candidates to the missing entries
+    let mut found: Vec<Option<PathBuf>> = vec![None; missing.len()];
+    let results = sched::hash_files(candidates.iter().map(|(_, path)| path.clone()), cfg);
+    for ((len, path), result) in candidates.iter().zip(results) {
+        if let Ok(crc32) = result {
+            let index = (0..missing.len()).find(|&i| {
+                found[i].is_none()
+                    && missing[i].crc32() == crc32
+                    && sizes[i].map(|size| Some(size) == *len).unwrap_or(true)
+            });
+            if let Some(i) = index {
+                found[i] = Some(path.strip_prefix(workdir).unwrap_or(path).to_path_buf());
+            }
+        }
+    }
+
+    // report the files that were found
+    for (entry, path) in missing.iter().zip(&found).filter(|_| verbose) {
+        if let Some(path) = path {
+            if cfg.quiet {
+                writeln!(
+                    cfg.stdout,
+                    "cksfv: {}: Missing, probably renamed to {}",
+                    entry.path().display(),
+                    path.display()
+                )?;
+            } else {
+                writeln!(
+                    cfg.stdout,
+                    "{:<50}missing, probably renamed to {}",
+                    entry.path().display(),
+                    path.display()
+                )?;
+            }
+        }
+    }
+
+    // record the new names in the listing
+    if cfg.rewrite && found.iter().any(Option::is_some) {
+        for line in listing.lines_mut() {
+            if let Line::Entry(entry) = line {
+                let index = missing.iter().position(|e| e == &entry);
+                if let Some(path) = index.and_then(|i| found[i].clone()) {
+                    *entry = Entry::new(path, entry.crc32());
+                }
+            }
+        }
+        if let Err(err) = listing.save(sfv) {
+            writeln!(cfg.stderr, "cksfv: {}: {}", sfv.display(), err)?;
+        }
+    }
+
+    Ok(found)
+}
+
+/// Format the result line of a verified file in the classic `cksfv` style.
+fn verify_line(file: &FileReport, quiet: bool) -> String {
+    let path = file.path.display();
+    let error = file.error.as_deref().unwrap_or_default();
+    match file.status {
+        Status::Ok => format!("{:<50}OK", path),
+        Status::Mismatch if quiet => format!("{:<50}different CRC", path),
+        Status::Mismatch => format!("cksfv: {}: Has a different CRC", path),
+        _ if quiet => format!("cksfv: {}: {}", path, error),
+        _ => format!("{:<50}{:<30}", path, error),
+    }
+}
+
+/// Report the results of checking a SFV listing whose files are being hashed.
+///
+/// `results` must yield the hashing results of the files of `entries`, in
+/// the same order, resolved relative to `workdir`. The listing at `sfv` is
+/// reported under `name`. Files of `workdir` not covered by the listing are
+/// reported if `orphans` is true, and missing files are searched in the
+/// tree under `workdir` if enabled in `cfg`, among the files that are not
+/// in `covered` (the files of this listing if `None`). Results are recorded
+/// in `report` if given, instead of being written as text.
+#[allow(clippy::too_many_arguments)]
+fn check_listing(
+    sfv: &Path,
+    name: &Path,
+    workdir: &Path,
+    entries: Result<Vec<Entry>, IoError>,
+    results: &mut HashResults,
+    orphans: bool,
+    covered: Option<&HashSet<String>>,
+    mut report: Option<&mut Report>,
+    stats: &mut Stats,
+    cfg: &mut Config,
+) -> Result<bool, IoError> {
+    // print the terminal "UI"
+    if report.is_none() {
+        writeln!(
+            cfg.stderr,
+            "--( Verifying: {} ){}",
+            name.display(),
+            "-".repeat(63 - min(63, name.display().to_string().len()))
+        )?;
+    }
+
+    // check the SFV listing could be opened
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => {
+            cfg.failures.record_error(&err);
+            if let Some(report) = report {
+                report.record(FileReport::new(Some(sfv), sfv, sfv, None, &Err(err)))?;
+            } else {
+                writeln!(cfg.stderr, "cksfv: {}: {}", name.display(), err)?;
+            }
+            return Ok(false);
+        }
+    };
+
+    // compare the current CRC32 of each file against the recorded one
+    let mut success = true;
+    let mut checked = 0;
+    let mut missing = Vec::new();
+    let mut located = Vec::new();
+    let color = cfg.color.enabled(&cfg.stdout);
+    for entry in entries.iter() {
+        let filename = entry.path();
+        if let Some(report) = report.as_deref_mut() {
+            report.start_file(Some(sfv), filename)?;
+        }
+        let result = match results.next() {
+            Some(result) => result,
+            None => break,
+        };
+        checked += 1;
+        match &result {
+            Ok(crc32) if *crc32 != entry.crc32() => cfg.failures.record(Failure::Mismatch),
+            Ok(_) => (),
+            Err(err) => cfg.failures.record_error(err),
+        }
+        if matches!(&result, Err(err) if err.kind() == std::io::ErrorKind::NotFound) {
+            missing.push(entry);
+        }
+        let file = workdir.join(filename);
+        let record = FileReport::new(Some(sfv), filename, &file, Some(entry.crc32()), &result);
+        let record = record.with_elapsed(results.elapsed());
+        success &= record.status == Status::Ok;
+        if let Some(report) = report.as_deref_mut() {
+            // missing files are recorded once we know whether they were renamed
+            if cfg.locate && record.status == Status::Missing {
+                located.push(record);
+            } else {
+                report.record(record)?;
+            }
+            continue;
+        }
+        if cfg.stats {
+            stats.record(&record);
+        }
+        if cfg.quiet && record.status == Status::Ok {
+            continue;
+        }
+        let style = match record.status {
+            Status::Ok => Style::Ok,
+            Status::Mismatch => Style::Mismatch,
+            _ => Style::Error,
+        };
+        let line = match &cfg.template {
+            Some(template) => template.render(&record, &file),
+            None => verify_line(&record, cfg.quiet),
+        };
+        writeln!(cfg.stdout, "{}", style.paint(line, color))?;
+    }
+
+    // report the files in the working directory missing from the listing
+    let listed = entries
+        .iter()
+        .map(|e| path_key(&workdir.join(e.path()), cfg.ignore_case))
+        .collect();
+    if orphans && !cfg.cancellation.is_cancelled() {
+        success &= report_orphans(workdir, 1, &listed, report.as_deref_mut(), cfg)?;
+    }
+
+    // look for the missing files under another name
+    let mut found = vec![None; missing.len()];
+    if cfg.locate && !missing.is_empty() && !cfg.cancellation.is_cancelled() {
+        let covered = covered.unwrap_or(&listed);
+        found = locate_missing(sfv, workdir, &missing, covered, report.is_none(), cfg)?;
+    }
+    if let Some(report) = report.as_deref_mut() {
+        for (record, renamed) in located.into_iter().zip(found) {
+            report.record(record.with_renamed(renamed))?;
+        }
+    }
 
     // add result message
+    if report.is_some() {
+        return Ok(success && !cfg.cancellation.is_cancelled());
+    }
     writeln!(cfg.stderr, "{}", "-".repeat(80))?;
+    if checked < entries.len() && cfg.cancellation.is_cancelled() {
+        success = false;
+        writeln!(
+            cfg.stdout,
+            "Interrupted after checking {} of {} files",
+            checked,
+            entries.len()
+        )?;
+    }
     if !cfg.quiet {
         if success {
-            writeln!(cfg.stdout, "Everything OK")?;
+            let line = Style::Ok.paint(String::from("Everything OK"), color);
+            writeln!(cfg.stdout, "{}", line)?;
         } else {
-            writeln!(cfg.stdout, "Errors Occured")?;
+            let line = Style::Mismatch.paint(String::from("Errors Occured"), color);
+            writeln!(cfg.stdout, "{}", line)?;
+        }
+    }
+    Ok(success)
+}
+
+/// Check a SFV listing at the given location, optionally using `workdir`.
+///
+/// This function always writes some progress messages to `config.stderr`, and
+/// outputs a message line for each file it checks to `config.stdout`. If
+/// enabled, the files of `workdir` that the listing does not cover are
+/// reported as well.
+pub fn cksfv<'a, F, C>(
+    sfv: &Path,
+    workdir: Option<&Path>,
+    config: C,
+    files: Option<F>,
+) -> Result<bool, IoError>
+where
+    F: IntoIterator<Item = &'a Path>,
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+
+    if let Some(_files) = files {
+        // only check the files given as arguments
+        unimplemented!("TODO: checking with file arguments");
+    }
+
+    // read the listing and start hashing the files it contains
+    let workdir = workdir.filter(|p| !p.as_os_str().is_empty());
+    let workdir = workdir.unwrap_or_else(|| Path::new("."));
+    let entries = read_listing(sfv, Path::new(""), &cfg.filter);
+    let paths = entries.iter().flatten().map(|e| workdir.join(e.path()));
+    let mut results = sched::hash_files(paths, &cfg);
+
+    let orphans = cfg.orphans;
+    let mut report = cfg.format.report(Operation::Verify, cfg.stdout.clone())?;
+    let mut stats = Stats::new();
+    let success = check_listing(
+        sfv,
+        sfv,
+        workdir,
+        entries,
+        &mut results,
+        orphans,
+        None,
+        report.as_mut(),
+        &mut stats,
+        &mut cfg,
+    )?;
+    if let Some(report) = report {
+        report.finish(cfg.cancellation.is_cancelled())?;
+    } else if cfg.stats {
+        stats.write(&mut cfg.stdout, "Statistics")?;
+    }
+    Ok(success)
+}
+
+/// Recursively check all the SFV listings found under `root`.
+///
+/// Files from all the listings are hashed together, so that listings located
+/// on different devices are checked in parallel. The results are reported one
+/// listing after the other, in the same format as [`cksfv`], each preceded
+/// by the directory the listing was found in. If enabled, the files of the
+/// tree that no listing covers are reported at the end.
+pub fn cksfv_recursive<C>(root: &Path, config: C) -> Result<bool, IoError>
+where
+    C: Into<Option<Config>>,
+{
+    // get a default config if none provided.
+    let mut cfg: Config = config.into().unwrap_or_default();
+
+    // find and read all the SFV listings in the directory tree
+    let mut listings = Vec::new();
+    let ignores = RefCell::new(IgnoreFiles::new(root));
+    let it = walkdir::WalkDir::new(root)
+        .follow_links(cfg.follow_links)
+        .sort_by(|a, b| a.depth().cmp(&b.depth()))
+        .into_iter()
+        .filter_entry(|entry| {
+            let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            !cfg.filter.is_excluded(path) && !is_ignored(entry, &mut ignores.borrow_mut())
+        });
+    for entry in it.flat_map(Result::ok) {
+        if is_sfv(entry.path()) {
+            let workdir = entry.path().parent().unwrap().to_path_buf();
+            let prefix = workdir.strip_prefix(root).unwrap_or(&workdir);
+            let entries = read_listing(entry.path(), prefix, &cfg.filter);
+            listings.push((workdir, entry.into_path(), entries));
         }
     }
+    report_ignore_errors(ignores.into_inner(), &mut cfg)?;
+
+    // start hashing the files of every listing
+    let paths = listings.iter().flat_map(|(workdir, _, entries)| {
+        entries
+            .iter()
+            .flatten()
+            .map(move |e| workdir.join(e.path()))
+    });
+    let mut results = sched::hash_files(paths, &cfg);
+
+    // record the files covered by every listing
+    let ignore_case = cfg.ignore_case;
+    let listed: HashSet<String> = listings
+        .iter()
+        .flat_map(|(workdir, _, entries)| {
+            entries
+                .iter()
+                .flatten()
+                .map(move |e| path_key(&workdir.join(e.path()), ignore_case))
+        })
+        .collect();
+
+    // report the results of each listing in order
+    let mut success = true;
+    let mut report = cfg.format.report(Operation::Verify, cfg.stdout.clone())?;
+    let mut stats = Stats::new();
+    for (workdir, sfv, entries) in listings {
+        if report.is_none() {
+            writeln!(cfg.stderr, "Entering directory: {}", workdir.display())?;
+        }
+        let name = sfv.strip_prefix(&workdir).unwrap();
+        let mut subtotal = Stats::subtotal();
+        success &= check_listing(
+            &sfv,
+            name,
+            &workdir,
+            entries,
+            &mut results,
+            false,
+            Some(&listed),
+            report.as_mut(),
+            &mut subtotal,
+            &mut cfg,
+        )?;
+        if cfg.stats && report.is_none() {
+            let title = format!(
+                "Subtotal for {}",
+                sfv.strip_prefix(root).unwrap_or(&sfv).display()
+            );
+            subtotal.write(&mut cfg.stdout, &title)?;
+            stats.merge(&subtotal, workdir.strip_prefix(root).unwrap_or(&workdir));
+        }
+        if cfg.cancellation.is_cancelled() {
+            success = false;
+            break;
+        }
+    }
+
+    // report the files of the tree not covered by any listing
+    if cfg.orphans && !cfg.cancellation.is_cancelled() {
+        if report.is_none() {
+            writeln!(
+                cfg.stderr,
+                "--( Unlisted: {} ){}",
+                root.display(),
+                "-".repeat(64 - min(64, root.display().to_string().len()))
+            )?;
+        }
+        success &= report_orphans(root, usize::MAX, &listed, report.as_mut(), &mut cfg)?;
+        if report.is_none() {
+            writeln!(cfg.stderr, "{}", "-".repeat(80))?;
+        }
+    }
+
+    if let Some(report) = report {
+        report.finish(cfg.cancellation.is_cancelled())?;
+    } else if cfg.stats {
+        stats.write(&mut cfg.stdout, "Statistics")?;
+    }
     Ok(success)
 }
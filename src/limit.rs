@@ -0,0 +1,71 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A bandwidth limiter shared between all the hashing workers.
+///
+/// The limiter keeps track of the instant at which the next byte may be
+/// read, and makes callers sleep until the bytes they consumed would have
+/// been available at the configured rate. The rate can be changed at any
+/// time, including while other threads are waiting on the limiter.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: AtomicU64,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Create a new limiter allowing `rate` bytes per second.
+    ///
+    /// A rate of `0` disables the limit entirely.
+    pub fn new(rate: u64) -> Self {
+        RateLimiter {
+            rate: AtomicU64::new(rate),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Get the current rate, in bytes per second.
+    pub fn rate(&self) -> u64 {
+        self.rate.load(Ordering::Relaxed)
+    }
+
+    /// Change the rate, in bytes per second.
+    pub fn set_rate(&self, rate: u64) {
+        self.rate.store(rate, Ordering::Relaxed);
+    }
+
+    /// Account for `n` bytes being read, sleeping if the limit was exceeded.
+    pub fn consume(&self, n: usize) {
+        let rate = self.rate();
+        if rate == 0 || n == 0 {
+            return;
+        }
+
+        // reserve a time slot for the bytes we just read
+        let cost = Duration::from_secs_f64(n as f64 / rate as f64);
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            if *next < now {
+                *next = now;
+            }
+            let wait = *next - now;
+            *next += cost;
+            wait
+        };
+
+        // wait until the slot is reached
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
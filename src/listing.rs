@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use getset::CopyGetters;
+use getset::Getters;
+
+use super::Crc32;
+
+/// A file and its CRC32, as recorded in a SFV listing.
+#[derive(Clone, Debug, PartialEq, Eq, Getters, CopyGetters)]
+pub struct Entry {
+    #[get = "pub"]
+    path: PathBuf,
+    #[get_copy = "pub"]
+    crc32: Crc32,
+}
+
+impl Entry {
+    /// Create a new entry for the given file.
+    pub fn new<P: Into<PathBuf>>(path: P, crc32: Crc32) -> Self {
+        Entry {
+            path: path.into(),
+            crc32,
+        }
+    }
+
+    /// Check whether the entry refers to the given path.
+    ///
+    /// Paths are compared ignoring their `.` components.
+    pub fn matches(&self, path: &Path, ignore_case: bool) -> bool {
+        path_key(&self.path, ignore_case) == path_key(path, ignore_case)
+    }
+}
+
+/// A line of a SFV listing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// A comment or a blank line, stored verbatim.
+    Comment(String),
+    /// A file entry.
+    Entry(Entry),
+}
+
+/// A difference between two SFV listings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// An entry only found in the new listing.
+    Added(Entry),
+    /// An entry only found in the old listing.
+    Removed(Entry),
+    /// An entry found in both listings with a different CRC.
+    Changed { old: Entry, new: Entry },
+    /// An entry of the old listing found under another path in the new one.
+    Renamed { old: Entry, new: Entry },
+}
+
+impl Change {
+    /// Get the path the change is sorted by.
+    fn path(&self) -> &Path {
+        match self {
+            Change::Added(entry) | Change::Removed(entry) => entry.path(),
+            Change::Changed { old, .. } | Change::Renamed { old, .. } => old.path(),
+        }
+    }
+}
+
+/// A SFV listing, preserving comments and the order of lines.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Listing {
+    lines: Vec<Line>,
+}
+
+impl Listing {
+    /// Create a new empty listing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the SFV listing at the given location.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        File::open(path).map(BufReader::new).and_then(Self::parse)
+    }
+
+    /// Parse a SFV listing from a reader.
+    ///
+    /// Lines that are neither comments nor valid entries give an error of
+    /// kind `InvalidData`.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self, IoError> {
+        let mut lines = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let trimmed = line.trim_end();
+            if trimmed.is_empty() || trimmed.starts_with(';') {
+                lines.push(Line::Comment(line));
+                continue;
+            }
+            let entry = trimmed
+                .rfind(' ')
+                .filter(|&i| i > 0)
+                .and_then(|i| {
+                    let crc32 = u32::from_str_radix(&trimmed[i + 1..], 16).ok()?;
+                    Some(Entry::new(&trimmed[..i], crc32))
+                })
+                .ok_or_else(|| {
+                    let msg = format!("line {}: invalid entry: {:?}", i + 1, trimmed);
+                    IoError::new(ErrorKind::InvalidData, msg)
+                })?;
+            lines.push(Line::Entry(entry));
+        }
+        Ok(Listing { lines })
+    }
+
+    /// Get the lines of the listing.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Get a mutable reference to the lines of the listing.
+    pub fn lines_mut(&mut self) -> &mut Vec<Line> {
+        &mut self.lines
+    }
+
+    /// Iterate over the file entries of the listing.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            Line::Comment(_) => None,
+        })
+    }
+
+    /// Check whether the listing has an entry for the given path.
+    pub fn contains(&self, path: &Path, ignore_case: bool) -> bool {
+        self.entries().any(|e| e.matches(path, ignore_case))
+    }
+
+    /// Compare the entries of this listing with the entries of a newer one.
+    ///
+    /// If `renames` is true, entries removed from this listing are paired
+    /// with entries added to the new listing having the same CRC, and
+    /// reported as renames. Changes are sorted by path.
+    pub fn diff(&self, new: &Listing, renames: bool, ignore_case: bool) -> Vec<Change> {
+        let key = |entry: &Entry| path_key(entry.path(), ignore_case);
+        let old_entries = self
+            .entries()
+            .map(|e| (key(e), e))
+            .collect::<HashMap<_, _>>();
+        let new_entries = new
+            .entries()
+            .map(|e| (key(e), e))
+            .collect::<HashMap<_, _>>();
+
+        let mut changes = Vec::new();
+        let mut removed = Vec::new();
+        for entry in self.entries() {
+            match new_entries.get(&key(entry)) {
+                None => removed.push(entry),
+                Some(other) if other.crc32 != entry.crc32 => changes.push(Change::Changed {
+                    old: entry.clone(),
+                    new: (*other).clone(),
+                }),
+                Some(_) => (),
+            }
+        }
+        let mut added = new
+            .entries()
+            .filter(|e| !old_entries.contains_key(&key(e)))
+            .map(Some)
+            .collect::<Vec<_>>();
+
+        // pair removed and added entries with the same CRC
+        for entry in removed {
+            let renamed = if renames {
+                added
+                    .iter_mut()
+                    .find(|other| other.map(|o| o.crc32 == entry.crc32).unwrap_or(false))
+                    .and_then(Option::take)
+            } else {
+                None
+            };
+            changes.push(match renamed {
+                Some(other) => Change::Renamed {
+                    old: entry.clone(),
+                    new: other.clone(),
+                },
+                None => Change::Removed(entry.clone()),
+            });
+        }
+        changes.extend(added.into_iter().flatten().cloned().map(Change::Added));
+
+        changes.sort_by(|a, b| a.path().cmp(b.path()));
+        changes
+    }
+
+    /// Write the listing to the given writer.
+    pub fn write<W: Write>(&self, mut out: W) -> Result<(), IoError> {
+        for line in &self.lines {
+            match line {
+                Line::Comment(comment) => writeln!(out, "{}", comment)?,
+                Line::Entry(entry) => {
+                    writeln!(out, "{} {:08X}", entry.path.display(), entry.crc32)?
+                }
+            }
+        }
+        out.flush()
+    }
+
+    /// Save the listing to the given location, replacing it atomically.
+    ///
+    /// The listing is first written to a temporary file in the same
+    /// directory, which is then renamed to the final location.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), IoError> {
+        let path = path.as_ref();
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.tmp", std::process::id()));
+        let tmp = path.with_file_name(name);
+        let result = File::create(&tmp)
+            .and_then(|f| self.write(BufWriter::new(f)))
+            .and_then(|_| std::fs::rename(&tmp, path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result
+    }
+}
+
+/// Get a key identifying a path, to compare listed files and found files.
+pub(crate) fn path_key(path: &Path, ignore_case: bool) -> String {
+    let path: PathBuf = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let key = path.to_string_lossy().into_owned();
+    if ignore_case {
+        key.to_lowercase()
+    } else {
+        key
+    }
+}
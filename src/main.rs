@@ -6,17 +6,231 @@ extern crate crc32fast;
 #[cfg(feature = "mmap")]
 extern crate memmap;
 
-use std::io::Write;
+use std::fs::File;
+use std::io::IsTerminal;
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
+use clap::error::ErrorKind;
 use clap::Arg;
 use clap::ArgAction;
+use clap::ArgGroup;
 use clap::Command;
 
+use cksfv::benchmark;
 use cksfv::cksfv;
+use cksfv::cksfv_recursive;
+use cksfv::diff;
+use cksfv::fix;
+use cksfv::merge;
 use cksfv::newsfv;
+use cksfv::newsfv_per_directory;
+use cksfv::prune;
+use cksfv::update;
+use cksfv::Backend;
+use cksfv::CancellationToken;
+use cksfv::Color;
 use cksfv::Config;
+use cksfv::Entry;
+use cksfv::Failures;
+use cksfv::Filter;
+use cksfv::Format;
 use cksfv::Output;
+use cksfv::RateLimiter;
+use cksfv::Template;
+
+/// Parse a bandwidth given in bytes per second, with an optional suffix.
+fn parse_rate(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((i, 'k')) | Some((i, 'K')) => (&value[..i], 1 << 10),
+        Some((i, 'm')) | Some((i, 'M')) => (&value[..i], 1 << 20),
+        Some((i, 'g')) | Some((i, 'G')) => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid rate: {:?}", value))
+}
+
+/// Get the path to the file storing the default backend.
+fn backend_file() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("cksfv.rs").join("backend"))
+}
+
+/// Load the default backend saved by a previous benchmark, if any.
+fn load_backend() -> Option<Backend> {
+    let contents = std::fs::read_to_string(backend_file()?).ok()?;
+    contents.parse().ok()
+}
+
+/// Save the given backend as the default for later runs.
+fn save_backend(backend: Backend) -> Result<PathBuf, std::io::Error> {
+    let path = backend_file().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "no configuration directory")
+    })?;
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(&path, format!("{}\n", backend))?;
+    Ok(path)
+}
+
+/// Halve or double the bandwidth limit on `SIGUSR1` and `SIGUSR2`.
+///
+/// An unlimited rate is left untouched by both signals.
+#[cfg(unix)]
+fn register_bwlimit_signals(limiter: Arc<RateLimiter>) {
+    use signal_hook::consts::SIGUSR1;
+    use signal_hook::consts::SIGUSR2;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = match Signals::new([SIGUSR1, SIGUSR2]) {
+        Ok(signals) => signals,
+        Err(err) => {
+            eprintln!("cksfv: cannot handle SIGUSR1 and SIGUSR2: {}", err);
+            return;
+        }
+    };
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            let rate = limiter.rate();
+            match signal {
+                _ if rate == 0 => (),
+                SIGUSR1 => limiter.set_rate(std::cmp::max(rate / 2, 1)),
+                SIGUSR2 => limiter.set_rate(rate.saturating_mul(2)),
+                _ => unreachable!(),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn register_bwlimit_signals(_limiter: Arc<RateLimiter>) {}
+
+/// Cancel the running operation on `SIGINT` or `SIGTERM`.
+///
+/// A second signal terminates the process immediately. The returned value
+/// holds the first signal received, or `0` if none was.
+#[cfg(unix)]
+fn register_interrupt_signals(token: CancellationToken) -> Arc<AtomicI32> {
+    use signal_hook::consts::SIGINT;
+    use signal_hook::consts::SIGTERM;
+    use signal_hook::iterator::Signals;
+
+    let received = Arc::new(AtomicI32::new(0));
+    let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+        Ok(signals) => signals,
+        Err(err) => {
+            eprintln!("cksfv: cannot handle SIGINT and SIGTERM: {}", err);
+            return received;
+        }
+    };
+    let first = received.clone();
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if first.swap(signal, Ordering::SeqCst) != 0 {
+                std::process::exit(128 + signal);
+            }
+            token.cancel();
+        }
+    });
+    received
+}
+
+#[cfg(not(unix))]
+fn register_interrupt_signals(_token: CancellationToken) -> Arc<AtomicI32> {
+    Arc::new(AtomicI32::new(0))
+}
+
+/// Open an inherited file descriptor for writing.
+#[cfg(unix)]
+fn open_fd(fd: i32) -> Result<File, std::io::Error> {
+    use std::os::unix::io::FromRawFd;
+    if fd < 0 {
+        return Err(std::io::Error::from_raw_os_error(9));
+    }
+    // check the descriptor is open before taking ownership of it
+    let file = unsafe { File::from_raw_fd(fd) };
+    match file.metadata() {
+        Ok(_) => Ok(file),
+        Err(err) => {
+            std::mem::forget(file);
+            Err(err)
+        }
+    }
+}
+
+/// Open an inherited file descriptor for writing.
+#[cfg(not(unix))]
+fn open_fd(_fd: i32) -> Result<File, std::io::Error> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "file descriptors are only supported on Unix",
+    ))
+}
+
+/// Ask on the terminal whether a mismatched entry should be fixed.
+fn confirm_fix(entry: &Entry, crc32: u32) -> bool {
+    eprint!(
+        "{}: {:08X} -> {:08X}, update? [y/N] ",
+        entry.path().display(),
+        entry.crc32(),
+        crc32
+    );
+    let mut answer = String::new();
+    match std::io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => {
+            eprintln!();
+            false
+        }
+        Ok(_) => matches!(answer.trim(), "y" | "Y" | "yes"),
+    }
+}
+
+/// The exit code of usage errors.
+const EXIT_USAGE: i32 = 64;
+
+/// The state deciding the exit code of the program.
+struct Status {
+    /// The first interruption signal received, or `0`.
+    interrupted: Arc<AtomicI32>,
+    /// The classes of failures encountered by the operation.
+    failures: Failures,
+    /// Whether to only exit with `0` or `1`, like the original `cksfv`.
+    legacy: bool,
+}
+
+/// Exit with a status reflecting the result, or the interruption signal.
+///
+/// Unless in legacy mode, the exit code combines the bits of the failures
+/// encountered, or is `1` for failures without a class.
+fn exit(result: bool, status: &Status) -> ! {
+    match status.interrupted.load(Ordering::SeqCst) {
+        0 if status.legacy => std::process::exit(!result as i32),
+        0 => match status.failures.code() {
+            0 => std::process::exit(!result as i32),
+            code => std::process::exit(code),
+        },
+        signal => std::process::exit(128 + signal),
+    }
+}
+
+/// Exit after a usage error, or after printing the help or version.
+fn usage_error(err: clap::Error, legacy: bool) -> ! {
+    if !err.use_stderr() || legacy {
+        err.exit();
+    }
+    let _ = err.print();
+    std::process::exit(EXIT_USAGE)
+}
 
 fn main() -> ! {
     // read CLI arguments
@@ -81,7 +295,7 @@ fn main() -> ! {
         .arg(
             Arg::new("r")
                 .short('r')
-                .help("Recursively check .sfv files in subdirectories")
+                .help("Recursively check .sfv files in subdirectories, or list directory contents when creating an sfv")
                 .action(ArgAction::SetTrue)
                 .conflicts_with("f")
                 .conflicts_with("g"),
@@ -98,6 +312,218 @@ fn main() -> ! {
                 .help("Verbose, by default this option is on")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("bwlimit")
+                .long("bwlimit")
+                .value_name("rate")
+                .help("Limit reading speed to rate bytes per second")
+                .value_parser(parse_rate),
+        )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .value_name("n")
+                .help("Hash up to n files at once on each device")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("per-dir")
+                .long("per-dir")
+                .value_name("name")
+                .help("Recursively create an sfv named name in each directory")
+                .conflicts_with("f")
+                .conflicts_with("g"),
+        )
+        .arg(
+            Arg::new("update")
+                .long("update")
+                .value_name("file")
+                .help("Add files missing from the sfv file without rehashing listed files")
+                .conflicts_with_all(["f", "g", "per-dir"]),
+        )
+        .arg(
+            Arg::new("prune")
+                .long("prune")
+                .value_name("file")
+                .help("Remove entries of files that no longer exist from the sfv file")
+                .conflicts_with_all(["f", "g", "per-dir", "update", "file"]),
+        )
+        .arg(
+            Arg::new("fix")
+                .long("fix")
+                .value_name("file")
+                .help("Record the current CRC of the files whose entry in the sfv file differs")
+                .conflicts_with_all(["f", "g", "per-dir", "update", "prune", "file"]),
+        )
+        .arg(
+            Arg::new("yes")
+                .long("yes")
+                .help("Fix all the mismatched entries without asking")
+                .requires("fix")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("file")
+                .help("Write the fixed or merged sfv file to file")
+                .requires("edited"),
+        )
+        .arg(
+            Arg::new("diff")
+                .long("diff")
+                .value_names(["old", "new"])
+                .num_args(2)
+                .help("Report the entries added, removed or changed between two sfv files")
+                .conflicts_with_all(["f", "g", "per-dir", "update", "prune", "fix", "file"]),
+        )
+        .arg(
+            Arg::new("renames")
+                .long("renames")
+                .help("Detect renamed entries by their CRC when comparing sfv files")
+                .requires("diff")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("porcelain")
+                .long("porcelain")
+                .help("Report the differences between sfv files in a machine-readable format")
+                .requires("diff")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("merge")
+                .long("merge")
+                .value_name("file")
+                .num_args(1..)
+                .help("Merge several sfv files into one, reporting conflicting entries")
+                .conflicts_with_all(["f", "g", "per-dir", "update", "prune", "fix", "diff", "file"]),
+        )
+        .arg(
+            Arg::new("rebase")
+                .long("rebase")
+                .help("Make merged entries relative to the output instead of their sfv file")
+                .requires("merge")
+                .action(ArgAction::SetTrue),
+        )
+        .group(
+            ArgGroup::new("edited")
+                .args(["fix", "merge"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("include")
+                .long("include")
+                .value_name("pattern")
+                .help("Only process files matching the glob pattern")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .value_name("pattern")
+                .help("Skip files and directories matching the glob pattern")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("locate")
+                .long("locate")
+                .help("Look for missing files among the unlisted files with the same CRC")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("rewrite")
+                .long("rewrite")
+                .help("Record the new name of the missing files found with --locate")
+                .requires("locate")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("orphans")
+                .long("orphans")
+                .help("Report files not listed in the sfv file when verifying")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("Like --orphans, but treat files not listed as errors")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .value_name("name")
+                .help("Read files using the given backend, e.g. read:65536 or parallel:4")
+                .value_parser(value_parser!(Backend)),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("format")
+                .help("Write the results as text, json, ndjson, junit, tap, csv, tsv or html")
+                .value_parser(value_parser!(Format))
+                .default_value("text"),
+        )
+        .arg(
+            Arg::new("printf")
+                .long("printf")
+                .value_name("template")
+                .help("Write each result line with a template of %p, %b, %e, %c, %s, %S and %m")
+                .value_parser(value_parser!(Template)),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .help("Print statistics about the files checked at the end of verification")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("when")
+                .help("Color the results as auto, always or never")
+                .value_parser(value_parser!(Color))
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("report-fd")
+                .long("report-fd")
+                .value_name("fd")
+                .help("Write machine-readable results to the given file descriptor")
+                .value_parser(value_parser!(i32)),
+        )
+        .arg(
+            Arg::new("benchmark")
+                .long("benchmark")
+                .value_name("file")
+                .help("Measure the speed of each backend on file, or on a scratch file")
+                .num_args(0..=1)
+                .default_missing_value(""),
+        )
+        .arg(
+            Arg::new("save")
+                .long("save")
+                .help("Save the fastest backend found by --benchmark as the default")
+                .action(ArgAction::SetTrue)
+                .requires("benchmark"),
+        )
+        .arg(
+            Arg::new("legacy-exit-codes")
+                .long("legacy-exit-codes")
+                .help("Exit with 1 on any failure instead of a code per class of failure")
+                .action(ArgAction::SetTrue),
+        )
+        .after_help(
+            "Exit codes are 0 on success, or combine the following bits on failure:\n  \
+             1   a file has a different CRC, or the listings differ\n  \
+             2   a file or listing is missing\n  \
+             4   a file or listing could not be read or written\n  \
+             8   a listing contains an invalid entry\n  \
+             16  a file is not listed (with --strict)\n\
+             Usage errors exit with 64, panics with 101, and interruptions with 128+signal.",
+        )
         .arg(
             Arg::new("file")
                 .index(1)
@@ -105,24 +531,121 @@ fn main() -> ! {
                 .action(ArgAction::Append),
         );
 
-    let matches = command.clone().get_matches();
+    let matches = match command.clone().try_get_matches() {
+        Ok(matches) => matches,
+        Err(err) => {
+            let legacy = std::env::args_os().any(|arg| arg == "--legacy-exit-codes");
+            usage_error(err, legacy)
+        }
+    };
+    let legacy = matches.get_flag("legacy-exit-codes");
 
     // build config
     let mut config = Config::default();
     config.set_quiet(matches.get_flag("q"));
+    config.set_progress(!matches.get_flag("q") && std::io::stderr().is_terminal());
+    let status = Status {
+        interrupted: register_interrupt_signals(config.cancellation().clone()),
+        failures: config.failures().clone(),
+        legacy,
+    };
+    if let Some(backend) = matches
+        .get_one::<Backend>("backend")
+        .copied()
+        .or_else(load_backend)
+    {
+        config.set_backend(backend);
+    }
+    config.set_follow_links(matches.get_flag("L"));
+    config.set_ignore_case(matches.get_flag("i"));
+    config.set_orphans(matches.get_flag("orphans") || matches.get_flag("strict"));
+    config.set_strict(matches.get_flag("strict"));
+    config.set_locate(matches.get_flag("locate"));
+    config.set_format(*matches.get_one::<Format>("format").unwrap());
+    config.set_color(*matches.get_one::<Color>("color").unwrap());
+    config.set_stats(matches.get_flag("stats"));
+    config.set_template(matches.get_one::<Template>("printf").cloned());
+    if let Some(&fd) = matches.get_one::<i32>("report-fd") {
+        if *config.format() == Format::Text {
+            let msg = "--report-fd requires a machine-readable --format";
+            usage_error(command.error(ErrorKind::ArgumentConflict, msg), legacy);
+        }
+        match open_fd(fd) {
+            Ok(file) => config.set_stdout(Output::file(file)),
+            Err(err) => {
+                let msg = format!("invalid file descriptor {}: {}", fd, err);
+                usage_error(command.error(ErrorKind::InvalidValue, msg), legacy)
+            }
+        };
+    }
+    config.set_rewrite(matches.get_flag("rewrite"));
+    config.set_jobs(*matches.get_one::<u64>("jobs").unwrap() as usize);
+    let patterns = |id: &str| -> Vec<&String> {
+        matches
+            .get_many::<String>(id)
+            .into_iter()
+            .flatten()
+            .collect()
+    };
+    match Filter::new(&patterns("include"), &patterns("exclude")) {
+        Ok(filter) => config.set_filter(filter),
+        Err(err) => usage_error(command.error(ErrorKind::InvalidValue, err), legacy),
+    };
+    if let Some(&rate) = matches.get_one::<u64>("bwlimit") {
+        let limiter = Arc::new(RateLimiter::new(rate));
+        register_bwlimit_signals(limiter.clone());
+        config.set_bwlimit(Some(limiter));
+    }
 
-    // check files recursively
-    if matches.get_flag("r") {
-        // get the base directory
-        let cwd = std::env::current_dir().unwrap();
+    // measure the speed of each backend
+    if let Some(file) = matches.get_one::<String>("benchmark") {
+        let file = Some(Path::new(file)).filter(|f| !f.as_os_str().is_empty());
+        let fastest = match benchmark(file, config.clone()) {
+            Ok(backend) => backend,
+            Err(err) => {
+                eprintln!("cksfv: {}", err);
+                status.failures.record_error(&err);
+                exit(false, &status);
+            }
+        };
+        if matches.get_flag("save") {
+            match save_backend(fastest) {
+                Ok(path) => eprintln!(
+                    "cksfv: saved {} as the default backend in {}",
+                    fastest,
+                    path.display()
+                ),
+                Err(err) => {
+                    eprintln!("cksfv: {}", err);
+                    status.failures.record_error(&err);
+                    exit(false, &status);
+                }
+            }
+        }
+        std::process::exit(0);
+    }
 
-        // get the files to check if any
-        let files = matches
-            .get_many::<&str>("file")
-            .map(|values| values.map(Path::new));
+    // check files recursively if no files to generate a listing for are given
+    if matches.get_flag("r")
+        && !matches.contains_id("file")
+        && !matches.contains_id("per-dir")
+        && !matches.contains_id("update")
+        && !matches.contains_id("prune")
+        && !matches.contains_id("fix")
+        && !matches.contains_id("diff")
+        && !matches.contains_id("merge")
+    {
+        // get the base directory, honoring `-C` if given
+        let cwd = std::env::current_dir().unwrap();
+        let root = match matches.get_one::<String>("C") {
+            Some(dir) => cwd.join(dir),
+            None => cwd,
+        };
 
         // assign the right output stream
-        if matches.get_flag("q") {
+        if *config.format() != Format::Text {
+            // keep machine-readable results on stdout
+        } else if matches.get_flag("q") {
             config.set_stderr(Output::devnull());
             config.set_stdout(Output::stderr());
         } else if !matches.get_flag("c") {
@@ -130,32 +653,8 @@ fn main() -> ! {
         }
 
         // recursively traverse the directory
-        let mut retcode = 0;
-        let it = walkdir::WalkDir::new(&cwd)
-            .follow_links(matches.get_flag("L"))
-            .sort_by(|a, b| a.depth().cmp(&b.depth()));
-        for entry in it.into_iter().flat_map(Result::ok) {
-            if entry
-                .path()
-                .extension()
-                .map(|x| x == "sfv")
-                .unwrap_or(false)
-            {
-                let workdir = entry.path().parent().unwrap();
-                let sfv = entry.path().strip_prefix(workdir).unwrap();
-                writeln!(
-                    config.stderr_mut(),
-                    "Entering directory: {}",
-                    workdir.display()
-                )
-                .unwrap();
-                std::env::set_current_dir(workdir).unwrap();
-                retcode *= 1 - cksfv(sfv, None, config.clone(), files.clone()).unwrap() as i32;
-            }
-        }
-
-        std::env::set_current_dir(&cwd).unwrap();
-        std::process::exit(retcode);
+        let result = cksfv_recursive(&root, config).unwrap();
+        exit(result, &status);
     }
 
     // check files using the given SFV listing
@@ -165,7 +664,7 @@ fn main() -> ! {
             .get_many::<String>("g")
             .or_else(|| matches.get_many::<String>("f"))
             .unwrap()
-            .last()
+            .next_back()
             .map(Path::new)
             .unwrap();
         let workdir = if matches.contains_id("g") {
@@ -180,7 +679,9 @@ fn main() -> ! {
             .map(|values| values.map(Path::new));
 
         // assign the right output stream
-        if matches.get_flag("q") {
+        if *config.format() != Format::Text {
+            // keep machine-readable results on stdout
+        } else if matches.get_flag("q") {
             config.set_stderr(Output::devnull());
             config.set_stdout(Output::stderr());
         } else if !matches.get_flag("c") {
@@ -189,19 +690,77 @@ fn main() -> ! {
 
         // run the operation
         let result = cksfv(sfv, workdir, config, files).unwrap();
-        std::process::exit(!result as i32);
+        exit(result, &status);
+    }
+
+    // add new files to an existing sfv file
+    if let Some(sfv) = matches.get_one::<String>("update") {
+        let files = matches
+            .get_many::<String>("file")
+            .map(|values| values.map(Path::new));
+        config.set_print_basename(matches.get_flag("b"));
+        config.set_recursive(matches.get_flag("r"));
+        let result = update(Path::new(sfv), files, config).unwrap();
+        exit(result, &status);
+    }
+
+    // remove the entries of missing files from an existing sfv file
+    if let Some(sfv) = matches.get_one::<String>("prune") {
+        let result = prune(Path::new(sfv), config).unwrap();
+        exit(result, &status);
+    }
+
+    // record the new CRC of changed files in an existing sfv file
+    if let Some(sfv) = matches.get_one::<String>("fix") {
+        let output = matches.get_one::<String>("output").map(Path::new);
+        let result = if matches.get_flag("yes") {
+            fix(Path::new(sfv), output, |_, _| true, config)
+        } else {
+            fix(Path::new(sfv), output, confirm_fix, config)
+        };
+        exit(result.unwrap(), &status);
+    }
+
+    // compare two sfv files
+    if let Some(mut listings) = matches.get_many::<String>("diff") {
+        let old = listings.next().map(Path::new).unwrap();
+        let new = listings.next().map(Path::new).unwrap();
+        config.set_renames(matches.get_flag("renames"));
+        config.set_porcelain(matches.get_flag("porcelain"));
+        let result = diff(old, new, config).unwrap();
+        exit(result, &status);
+    }
+
+    // merge several sfv files
+    if let Some(listings) = matches.get_many::<String>("merge") {
+        let output = matches.get_one::<String>("output").map(Path::new);
+        config.set_rebase(matches.get_flag("rebase"));
+        let result = merge(listings.map(Path::new), output, config).unwrap();
+        exit(result, &status);
+    }
+
+    // generate a new sfv file in each directory of the given trees
+    if let Some(name) = matches.get_one::<String>("per-dir") {
+        let roots = matches
+            .get_many::<String>("file")
+            .map(|values| values.map(Path::new).collect())
+            .unwrap_or_else(|| vec![Path::new(".")]);
+        let result = newsfv_per_directory(roots, name, config).unwrap();
+        exit(result, &status);
     }
 
     // generate a new sfv file if given files as input
     if let Some(files) = matches.get_many::<String>("file") {
         config.set_print_basename(matches.get_flag("b"));
+        config.set_recursive(matches.get_flag("r"));
         let result = newsfv(files.map(Path::new), config).unwrap();
-        std::process::exit(!result as i32);
+        exit(result, &status);
     }
 
-    // otherwise is no operation given exit with EINVAL
+    // otherwise is no operation given exit with EINVAL, or as a usage error
     match command.print_help() {
-        Ok(_) => std::process::exit(22),
+        Ok(_) if legacy => std::process::exit(22),
+        Ok(_) => std::process::exit(EXIT_USAGE),
         Err(e) => std::process::exit(e.raw_os_error().unwrap_or(1)),
     }
 }
@@ -0,0 +1,148 @@
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The width of the progress line, matching the width of the banners.
+const WIDTH: usize = 79;
+
+/// Format a number of bytes using binary prefixes.
+pub(crate) fn format_size(bytes: f64) -> String {
+    let mut size = bytes;
+    for unit in ["B", "KiB", "MiB", "GiB", "TiB"] {
+        if size < 1024.0 || unit == "TiB" {
+            return match unit {
+                "B" => format!("{:.0} {}", size, unit),
+                _ => format!("{:.1} {}", size, unit),
+            };
+        }
+        size /= 1024.0;
+    }
+    unreachable!()
+}
+
+/// Format a number of seconds as a duration.
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// The progress of a hashing run, shared between the hashing workers.
+#[derive(Debug)]
+pub(crate) struct Progress {
+    start: Instant,
+    total: u64,
+    done: AtomicU64,
+    current: Mutex<Option<PathBuf>>,
+}
+
+impl Progress {
+    /// Create a new progress tracker for a run reading `total` bytes.
+    pub fn new(total: u64) -> Self {
+        Progress {
+            start: Instant::now(),
+            total,
+            done: AtomicU64::new(0),
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Record that a worker started hashing the given file.
+    pub fn start_file(&self, file: &Path) {
+        *self.current.lock().unwrap() = Some(file.to_path_buf());
+    }
+
+    /// Record that `n` more bytes were hashed.
+    pub fn advance(&self, n: usize) {
+        self.done.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// Render the progress line, without any terminal control sequence.
+    fn render(&self) -> String {
+        let done = self.done.load(Ordering::Relaxed);
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = if rate > 0.0 {
+            format_duration(self.total.saturating_sub(done) as f64 / rate)
+        } else {
+            String::from("--:--")
+        };
+        let stats = format!(
+            "{} / {}  {}/s  ETA {}",
+            format_size(done as f64),
+            format_size(self.total as f64),
+            format_size(rate),
+            eta
+        );
+
+        // truncate the beginning of the filename if the line is too long
+        let file = match &*self.current.lock().unwrap() {
+            Some(path) => path.display().to_string(),
+            None => String::new(),
+        };
+        let room = WIDTH.saturating_sub(stats.chars().count() + 2);
+        let count = file.chars().count();
+        let file = if count > room {
+            let skip = count - room.saturating_sub(3);
+            let tail: String = file.chars().skip(skip).collect();
+            format!("...{}", tail)
+        } else {
+            file
+        };
+        format!("{:<width$}  {}", file, stats, width = room)
+    }
+
+    /// Draw the progress line on the given terminal.
+    pub fn draw<W: Write>(&self, out: &mut W) {
+        let _ = write!(out, "\r\x1b[2K{}", self.render());
+        let _ = out.flush();
+    }
+
+    /// Erase the progress line from the given terminal.
+    pub fn clear<W: Write>(&self, out: &mut W) {
+        let _ = write!(out, "\r\x1b[2K");
+        let _ = out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_size_boundaries() {
+        assert_eq!(format_size(0.0), "0 B");
+        assert_eq!(format_size(1023.0), "1023 B");
+        assert_eq!(format_size(1024.0), "1.0 KiB");
+        assert_eq!(format_size(1536.0), "1.5 KiB");
+        assert_eq!(format_size(1048576.0), "1.0 MiB");
+        assert_eq!(format_size(1024f64.powi(4)), "1.0 TiB");
+        assert_eq!(format_size(1024f64.powi(5)), "1024.0 TiB");
+    }
+
+    #[test]
+    fn format_duration_boundaries() {
+        assert_eq!(format_duration(0.0), "00:00");
+        assert_eq!(format_duration(59.4), "00:59");
+        assert_eq!(format_duration(60.0), "01:00");
+        assert_eq!(format_duration(3599.0), "59:59");
+        assert_eq!(format_duration(3600.0), "1:00:00");
+        assert_eq!(format_duration(36061.0), "10:01:01");
+    }
+}
@@ -0,0 +1,1040 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::DateTime;
+use chrono::Local;
+use chrono::SecondsFormat;
+
+use super::Crc32;
+use super::Output;
+
+/// The format of the results written by an operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Format {
+    /// The classic `cksfv` output, with one line per file.
+    #[default]
+    Text,
+    /// A single JSON document written once the operation is done.
+    Json,
+    /// A stream of JSON events written as the operation proceeds.
+    Ndjson,
+    /// A JUnit XML report, with a test suite for each listing.
+    Junit,
+    /// A TAP stream, with a test point for each file.
+    Tap,
+    /// A CSV table, with a row for each file.
+    Csv,
+    /// A TSV table, with a row for each file.
+    Tsv,
+    /// A self-contained HTML page, with a section for each listing.
+    Html,
+}
+
+impl Format {
+    /// Create a new report for an operation, or `None` for the text format.
+    pub(crate) fn report(
+        &self,
+        operation: Operation,
+        out: Output,
+    ) -> Result<Option<Report>, IoError> {
+        let reporter: Box<dyn Reporter> = match self {
+            Format::Text => return Ok(None),
+            Format::Json => Box::new(JsonReporter),
+            Format::Ndjson => Box::new(NdjsonReporter),
+            Format::Junit => Box::new(JunitReporter),
+            Format::Tap => Box::new(TapReporter::default()),
+            Format::Csv => Box::new(TableReporter::new(',')),
+            Format::Tsv => Box::new(TableReporter::new('\t')),
+            Format::Html => Box::new(HtmlReporter),
+        };
+        Report::new(operation, reporter, out).map(Some)
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+            Format::Ndjson => write!(f, "ndjson"),
+            Format::Junit => write!(f, "junit"),
+            Format::Tap => write!(f, "tap"),
+            Format::Csv => write!(f, "csv"),
+            Format::Tsv => write!(f, "tsv"),
+            Format::Html => write!(f, "html"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "junit" => Ok(Format::Junit),
+            "tap" => Ok(Format::Tap),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "html" => Ok(Format::Html),
+            _ => Err(format!("invalid format: {:?}", s)),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The kind of operation being reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Create,
+    Verify,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Operation::Create => write!(f, "create"),
+            Operation::Verify => write!(f, "verify"),
+        }
+    }
+}
+
+/// The outcome of processing a single file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Status {
+    /// The file was hashed, and matches its entry if any.
+    Ok,
+    /// The file was hashed but does not match its entry.
+    Mismatch,
+    /// The file of an entry does not exist.
+    Missing,
+    /// The file could not be read.
+    Error,
+    /// The file exists but is not covered by any listing.
+    Unlisted,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Status::Ok => write!(f, "ok"),
+            Status::Mismatch => write!(f, "mismatch"),
+            Status::Missing => write!(f, "missing"),
+            Status::Error => write!(f, "error"),
+            Status::Unlisted => write!(f, "unlisted"),
+        }
+    }
+}
+
+/// The result of processing a single file.
+#[derive(Clone, Debug)]
+pub(crate) struct FileReport {
+    pub listing: Option<PathBuf>,
+    pub path: PathBuf,
+    pub expected: Option<Crc32>,
+    pub actual: Option<Crc32>,
+    pub status: Status,
+    pub size: Option<u64>,
+    pub error: Option<String>,
+    pub elapsed: Option<Duration>,
+    pub renamed: Option<PathBuf>,
+}
+
+impl FileReport {
+    /// Create a new report from the result of hashing a file.
+    ///
+    /// `file` is the location the file was read from, and `path` the name
+    /// it is reported under.
+    pub fn new(
+        listing: Option<&Path>,
+        path: &Path,
+        file: &Path,
+        expected: Option<Crc32>,
+        result: &Result<Crc32, IoError>,
+    ) -> Self {
+        let (actual, error) = match result {
+            Ok(crc32) => (Some(*crc32), None),
+            Err(err) => (None, Some(err)),
+        };
+        let status = match error {
+            Some(err) if err.kind() == ErrorKind::NotFound => Status::Missing,
+            Some(_) => Status::Error,
+            None if expected.is_some() && expected != actual => Status::Mismatch,
+            None => Status::Ok,
+        };
+        FileReport {
+            listing: listing.map(Path::to_path_buf),
+            path: path.to_path_buf(),
+            expected,
+            actual,
+            status,
+            size: actual
+                .and_then(|_| std::fs::metadata(file).ok())
+                .map(|m| m.len()),
+            error: error.map(ToString::to_string),
+            elapsed: None,
+            renamed: None,
+        }
+    }
+
+    /// Set the time spent hashing the file.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed = Some(elapsed);
+        self
+    }
+
+    /// Set the path a missing file was probably renamed to.
+    pub fn with_renamed(mut self, renamed: Option<PathBuf>) -> Self {
+        self.renamed = renamed;
+        self
+    }
+
+    /// Create a new report for a file that is not covered by any listing.
+    pub fn unlisted(path: &Path, file: &Path) -> Self {
+        FileReport {
+            listing: None,
+            path: path.to_path_buf(),
+            expected: None,
+            actual: None,
+            status: Status::Unlisted,
+            size: std::fs::metadata(file).ok().map(|m| m.len()),
+            error: None,
+            elapsed: None,
+            renamed: None,
+        }
+    }
+}
+
+/// The number of files of each status processed during an operation.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Totals {
+    pub files: usize,
+    pub ok: usize,
+    pub mismatch: usize,
+    pub missing: usize,
+    pub error: usize,
+    pub unlisted: usize,
+    pub bytes: u64,
+}
+
+/// The metadata of an operation.
+#[derive(Clone, Debug)]
+pub(crate) struct Run {
+    pub operation: Operation,
+    pub started: DateTime<Local>,
+    pub start: Instant,
+    pub interrupted: bool,
+}
+
+// ---------------------------------------------------------------------------
+
+/// A writer for the results of an operation in a machine-readable format.
+///
+/// Streaming formats write the results as they are recorded, while document
+/// formats only write them once the operation is done.
+pub(crate) trait Reporter {
+    /// Check whether the reporter needs every result to write its document.
+    ///
+    /// Results are only kept until the end of the operation if this returns
+    /// `true`, otherwise [`Reporter::finish`] receives no results.
+    fn buffered(&self) -> bool {
+        false
+    }
+
+    /// Write the beginning of the operation.
+    fn start(&mut self, _out: &mut dyn Write, _run: &Run) -> Result<(), IoError> {
+        Ok(())
+    }
+
+    /// Write that the operation is waiting for a file to be processed.
+    fn file_started(
+        &mut self,
+        _out: &mut dyn Write,
+        _listing: Option<&Path>,
+        _path: &Path,
+    ) -> Result<(), IoError> {
+        Ok(())
+    }
+
+    /// Write the result of processing a file.
+    fn file(&mut self, _out: &mut dyn Write, _file: &FileReport) -> Result<(), IoError> {
+        Ok(())
+    }
+
+    /// Write that the file the operation was waiting for will not be
+    /// processed, because the operation was interrupted.
+    fn file_cancelled(
+        &mut self,
+        _out: &mut dyn Write,
+        _listing: Option<&Path>,
+        _path: &Path,
+    ) -> Result<(), IoError> {
+        Ok(())
+    }
+
+    /// Write the results once the operation is done.
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        run: &Run,
+        files: &[FileReport],
+        totals: &Totals,
+    ) -> Result<(), IoError>;
+}
+
+/// The results of an operation, collected as files are processed.
+pub(crate) struct Report {
+    run: Run,
+    files: Vec<FileReport>,
+    pending: Option<(Option<PathBuf>, PathBuf)>,
+    totals: Totals,
+    reporter: Box<dyn Reporter>,
+    out: Output,
+}
+
+impl Report {
+    /// Create a new report for the given operation, written to `out`.
+    fn new(
+        operation: Operation,
+        reporter: Box<dyn Reporter>,
+        out: Output,
+    ) -> Result<Self, IoError> {
+        let mut report = Report {
+            run: Run {
+                operation,
+                started: Local::now(),
+                start: Instant::now(),
+                interrupted: false,
+            },
+            files: Vec::new(),
+            pending: None,
+            totals: Totals::default(),
+            reporter,
+            out,
+        };
+        report.reporter.start(&mut report.out, &report.run)?;
+        report.out.flush()?;
+        Ok(report)
+    }
+
+    /// Record that the operation is waiting for a file to be processed.
+    pub fn start_file(&mut self, listing: Option<&Path>, path: &Path) -> Result<(), IoError> {
+        self.pending = Some((listing.map(Path::to_path_buf), path.to_path_buf()));
+        self.reporter.file_started(&mut self.out, listing, path)?;
+        self.out.flush()
+    }
+
+    /// Record the result of processing a file.
+    pub fn record(&mut self, report: FileReport) -> Result<(), IoError> {
+        self.totals.files += 1;
+        self.totals.bytes += report.size.unwrap_or(0);
+        match report.status {
+            Status::Ok => self.totals.ok += 1,
+            Status::Mismatch => self.totals.mismatch += 1,
+            Status::Missing => self.totals.missing += 1,
+            Status::Error => self.totals.error += 1,
+            Status::Unlisted => self.totals.unlisted += 1,
+        }
+        self.pending = None;
+        self.reporter.file(&mut self.out, &report)?;
+        self.out.flush()?;
+        if self.reporter.buffered() {
+            self.files.push(report);
+        }
+        Ok(())
+    }
+
+    /// Write the results of the operation.
+    ///
+    /// A file started but never recorded is reported as cancelled first.
+    pub fn finish(mut self, interrupted: bool) -> Result<(), IoError> {
+        self.run.interrupted = interrupted;
+        if let Some((listing, path)) = self.pending.take() {
+            self.reporter
+                .file_cancelled(&mut self.out, listing.as_deref(), &path)?;
+        }
+        self.reporter
+            .finish(&mut self.out, &self.run, &self.files, &self.totals)?;
+        self.out.flush()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Quote a string as a JSON string.
+fn json_string<S: AsRef<str>>(s: S) -> String {
+    let mut quoted = String::with_capacity(s.as_ref().len() + 2);
+    quoted.push('"');
+    for c in s.as_ref().chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Format an optional value as JSON, using `null` for `None`.
+fn json_option<T, F: Fn(T) -> String>(value: Option<T>, f: F) -> String {
+    value.map(f).unwrap_or_else(|| String::from("null"))
+}
+
+/// Format the result of processing a file as a JSON object.
+///
+/// The `renamed` field is only present for missing files that were located.
+fn json_file(file: &FileReport) -> String {
+    let path = |p: &PathBuf| json_string(p.to_string_lossy());
+    let crc32 = |c: Crc32| format!("\"{:08X}\"", c);
+    let renamed = file
+        .renamed
+        .as_ref()
+        .map(|p| format!(", \"renamed\": {}", path(p)))
+        .unwrap_or_default();
+    format!(
+        "{{\"listing\": {}, \"path\": {}, \"expected\": {}, \"actual\": {}, \"status\": \"{}\", \"size\": {}, \"error\": {}{}}}",
+        json_option(file.listing.as_ref(), path),
+        path(&file.path),
+        json_option(file.expected, crc32),
+        json_option(file.actual, crc32),
+        file.status,
+        json_option(file.size, |s| s.to_string()),
+        json_option(file.error.as_ref(), json_string),
+        renamed,
+    )
+}
+
+/// Format the metadata of an operation as a JSON object.
+fn json_run(run: &Run) -> String {
+    format!(
+        "{{\"program\": \"cksfv.rs\", \"version\": \"{}\", \"operation\": \"{}\", \"started\": \"{}\", \"elapsed\": {:.3}, \"interrupted\": {}}}",
+        crate_version!(),
+        run.operation,
+        run.started.to_rfc3339_opts(SecondsFormat::Secs, false),
+        run.start.elapsed().as_secs_f64(),
+        run.interrupted,
+    )
+}
+
+/// Format the totals of an operation as a JSON object.
+fn json_totals(totals: &Totals) -> String {
+    format!(
+        "{{\"files\": {}, \"ok\": {}, \"mismatch\": {}, \"missing\": {}, \"error\": {}, \"unlisted\": {}, \"bytes\": {}}}",
+        totals.files,
+        totals.ok,
+        totals.mismatch,
+        totals.missing,
+        totals.error,
+        totals.unlisted,
+        totals.bytes,
+    )
+}
+
+/// A reporter writing a single JSON document.
+struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn buffered(&self) -> bool {
+        true
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        run: &Run,
+        files: &[FileReport],
+        totals: &Totals,
+    ) -> Result<(), IoError> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"run\": {},", json_run(run))?;
+        writeln!(out, "  \"files\": [")?;
+        for (i, file) in files.iter().enumerate() {
+            let sep = if i + 1 < files.len() { "," } else { "" };
+            writeln!(out, "    {}{}", json_file(file), sep)?;
+        }
+        writeln!(out, "  ],")?;
+        writeln!(out, "  \"totals\": {}", json_totals(totals))?;
+        writeln!(out, "}}")
+    }
+}
+
+/// A reporter writing one JSON event per line as the operation proceeds.
+///
+/// Events are objects with an `event` field, which is one of `run_start`,
+/// `file_start`, `file_result`, `file_cancel` and `run_end`. Every
+/// `file_start` event is eventually followed by the `file_result` event of
+/// the same file, or by a `file_cancel` event if the operation was
+/// interrupted before the file was processed.
+struct NdjsonReporter;
+
+impl Reporter for NdjsonReporter {
+    fn start(&mut self, out: &mut dyn Write, run: &Run) -> Result<(), IoError> {
+        writeln!(
+            out,
+            "{{\"event\": \"run_start\", \"program\": \"cksfv.rs\", \"version\": \"{}\", \"operation\": \"{}\", \"started\": \"{}\"}}",
+            crate_version!(),
+            run.operation,
+            run.started.to_rfc3339_opts(SecondsFormat::Secs, false),
+        )
+    }
+
+    fn file_started(
+        &mut self,
+        out: &mut dyn Write,
+        listing: Option<&Path>,
+        path: &Path,
+    ) -> Result<(), IoError> {
+        writeln!(
+            out,
+            "{{\"event\": \"file_start\", \"listing\": {}, \"path\": {}}}",
+            json_option(listing, |p| json_string(p.to_string_lossy())),
+            json_string(path.to_string_lossy()),
+        )
+    }
+
+    fn file(&mut self, out: &mut dyn Write, file: &FileReport) -> Result<(), IoError> {
+        let object = json_file(file);
+        writeln!(out, "{{\"event\": \"file_result\", {}", &object[1..])
+    }
+
+    fn file_cancelled(
+        &mut self,
+        out: &mut dyn Write,
+        listing: Option<&Path>,
+        path: &Path,
+    ) -> Result<(), IoError> {
+        writeln!(
+            out,
+            "{{\"event\": \"file_cancel\", \"listing\": {}, \"path\": {}}}",
+            json_option(listing, |p| json_string(p.to_string_lossy())),
+            json_string(path.to_string_lossy()),
+        )
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        run: &Run,
+        _files: &[FileReport],
+        totals: &Totals,
+    ) -> Result<(), IoError> {
+        writeln!(
+            out,
+            "{{\"event\": \"run_end\", \"elapsed\": {:.3}, \"interrupted\": {}, \"totals\": {}}}",
+            run.start.elapsed().as_secs_f64(),
+            run.interrupted,
+            json_totals(totals),
+        )
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Group the results of an operation by listing, in order of appearance.
+fn group_by_listing(files: &[FileReport]) -> Vec<(Option<&PathBuf>, Vec<&FileReport>)> {
+    let mut groups: Vec<(Option<&PathBuf>, Vec<&FileReport>)> = Vec::new();
+    for file in files {
+        match groups.iter_mut().find(|(l, _)| *l == file.listing.as_ref()) {
+            Some((_, group)) => group.push(file),
+            None => groups.push((file.listing.as_ref(), vec![file])),
+        }
+    }
+    groups
+}
+
+/// Escape a string for use in XML text or attribute values.
+fn xml_escape<S: AsRef<str>>(s: S) -> String {
+    let mut escaped = String::with_capacity(s.as_ref().len());
+    for c in s.as_ref().chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {
+                escaped.push_str(&format!("&#x{:x};", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Format an optional CRC32 for use in a message.
+fn crc32_or_none(crc32: Option<Crc32>) -> String {
+    crc32
+        .map(|c| format!("{:08X}", c))
+        .unwrap_or_else(|| String::from("none"))
+}
+
+/// A reporter writing a JUnit XML document.
+///
+/// Each listing is written as a test suite, and each of its entries as a
+/// test case. Mismatched and missing files are failures, unreadable files
+/// are errors, and unlisted files are skipped test cases.
+struct JunitReporter;
+
+impl Reporter for JunitReporter {
+    fn buffered(&self) -> bool {
+        true
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        run: &Run,
+        files: &[FileReport],
+        totals: &Totals,
+    ) -> Result<(), IoError> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<testsuites name="cksfv.rs {}" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+            run.operation,
+            totals.files,
+            totals.mismatch + totals.missing,
+            totals.error,
+            run.start.elapsed().as_secs_f64(),
+        )?;
+        for (listing, cases) in group_by_listing(files) {
+            let name = match listing {
+                Some(path) => xml_escape(path.to_string_lossy()),
+                None => String::from("cksfv"),
+            };
+            let count = |status: Status| cases.iter().filter(|c| c.status == status).count();
+            writeln!(
+                out,
+                r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}" timestamp="{}">"#,
+                name,
+                cases.len(),
+                count(Status::Mismatch) + count(Status::Missing),
+                count(Status::Error),
+                count(Status::Unlisted),
+                run.started.format("%Y-%m-%dT%H:%M:%S"),
+            )?;
+            for case in cases {
+                write!(
+                    out,
+                    r#"    <testcase classname="{}" name="{}""#,
+                    name,
+                    xml_escape(case.path.to_string_lossy())
+                )?;
+                let message = match case.status {
+                    Status::Ok => None,
+                    Status::Mismatch => Some(("failure", "different CRC")),
+                    Status::Missing => Some(("failure", "missing file")),
+                    Status::Error => Some(("error", "unreadable file")),
+                    Status::Unlisted => Some(("skipped", "not listed")),
+                };
+                match message {
+                    None => writeln!(out, "/>")?,
+                    Some((tag, message)) => {
+                        writeln!(out, ">")?;
+                        write!(
+                            out,
+                            r#"      <{} type="{}" message="{}">expected: {}, actual: {}"#,
+                            tag,
+                            case.status,
+                            message,
+                            crc32_or_none(case.expected),
+                            crc32_or_none(case.actual),
+                        )?;
+                        if let Some(error) = &case.error {
+                            write!(out, ", error: {}", xml_escape(error))?;
+                        }
+                        if let Some(renamed) = &case.renamed {
+                            write!(out, ", renamed: {}", xml_escape(renamed.to_string_lossy()))?;
+                        }
+                        writeln!(out, "</{}>", tag)?;
+                        writeln!(out, "    </testcase>")?;
+                    }
+                }
+            }
+            writeln!(out, "  </testsuite>")?;
+        }
+        writeln!(out, "</testsuites>")
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// A reporter writing a TAP stream.
+///
+/// Test points are written as soon as each file is processed, so the plan
+/// is written at the end of the stream. Failures are followed by diagnostic
+/// lines, and a comment introduces the entries of each listing.
+#[derive(Default)]
+struct TapReporter {
+    count: usize,
+    listing: Option<PathBuf>,
+}
+
+impl Reporter for TapReporter {
+    fn file(&mut self, out: &mut dyn Write, file: &FileReport) -> Result<(), IoError> {
+        if let Some(listing) = file
+            .listing
+            .as_ref()
+            .filter(|l| self.listing.as_ref() != Some(l))
+        {
+            writeln!(out, "# {}", listing.display())?;
+            self.listing = Some(listing.clone());
+        }
+
+        self.count += 1;
+        let path = file.path.display().to_string().replace('#', "\\#");
+        match file.status {
+            Status::Ok => writeln!(out, "ok {} - {}", self.count, path),
+            Status::Unlisted => writeln!(out, "ok {} - {} # SKIP not listed", self.count, path),
+            Status::Mismatch => {
+                writeln!(out, "not ok {} - {}", self.count, path)?;
+                writeln!(
+                    out,
+                    "# different CRC: expected {}, got {}",
+                    crc32_or_none(file.expected),
+                    crc32_or_none(file.actual)
+                )
+            }
+            Status::Missing | Status::Error => {
+                writeln!(out, "not ok {} - {}", self.count, path)?;
+                let error = file.error.as_deref().unwrap_or("unknown error");
+                for line in error.lines() {
+                    writeln!(out, "# {}: {}", file.status, line)?;
+                }
+                if let Some(renamed) = &file.renamed {
+                    writeln!(out, "# probably renamed to {}", renamed.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        run: &Run,
+        _files: &[FileReport],
+        _totals: &Totals,
+    ) -> Result<(), IoError> {
+        if run.interrupted {
+            writeln!(out, "Bail out! Interrupted")
+        } else {
+            writeln!(out, "1..{}", self.count)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The columns of a table report.
+const TABLE_COLUMNS: &[&str] = &[
+    "listing", "path", "size", "expected", "actual", "status", "error", "elapsed", "renamed",
+];
+
+/// A reporter writing a table with a row for each file, as CSV or TSV.
+///
+/// CSV fields are quoted following RFC 4180 when they contain a comma, a
+/// quote or a line break. TSV fields cannot be quoted, so tabs, line breaks
+/// and backslashes are escaped with a backslash instead.
+struct TableReporter {
+    separator: char,
+}
+
+impl TableReporter {
+    /// Create a new table reporter using the given field separator.
+    fn new(separator: char) -> Self {
+        TableReporter { separator }
+    }
+
+    /// Escape a single field.
+    fn field<S: AsRef<str>>(&self, s: S) -> String {
+        let s = s.as_ref();
+        if self.separator == '\t' {
+            s.replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        } else if s.contains([self.separator, '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    }
+
+    /// Write a row of fields.
+    fn row(&self, out: &mut dyn Write, fields: &[String]) -> Result<(), IoError> {
+        let separator = self.separator.to_string();
+        writeln!(out, "{}", fields.join(&separator))
+    }
+}
+
+impl Reporter for TableReporter {
+    fn start(&mut self, out: &mut dyn Write, _run: &Run) -> Result<(), IoError> {
+        let header = TABLE_COLUMNS
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        self.row(out, &header)
+    }
+
+    fn file(&mut self, out: &mut dyn Write, file: &FileReport) -> Result<(), IoError> {
+        let crc32 = |c: Option<Crc32>| c.map(|c| format!("{:08X}", c)).unwrap_or_default();
+        let fields = [
+            file.listing
+                .as_ref()
+                .map(|l| self.field(l.to_string_lossy()))
+                .unwrap_or_default(),
+            self.field(file.path.to_string_lossy()),
+            file.size.map(|s| s.to_string()).unwrap_or_default(),
+            crc32(file.expected),
+            crc32(file.actual),
+            file.status.to_string(),
+            file.error
+                .as_ref()
+                .map(|e| self.field(e))
+                .unwrap_or_default(),
+            file.elapsed
+                .map(|e| format!("{:.6}", e.as_secs_f64()))
+                .unwrap_or_default(),
+            file.renamed
+                .as_ref()
+                .map(|r| self.field(r.to_string_lossy()))
+                .unwrap_or_default(),
+        ];
+        self.row(out, &fields)
+    }
+
+    fn finish(
+        &mut self,
+        _out: &mut dyn Write,
+        _run: &Run,
+        _files: &[FileReport],
+        _totals: &Totals,
+    ) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// The stylesheet of the HTML report.
+const HTML_STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+th { background: #eee; }
+table.sortable th { cursor: pointer; }
+td.mono { font-family: monospace; }
+.ok { color: #2a7a2a; }
+.mismatch, .missing, .error { color: #b22; }
+.unlisted { color: #a60; }
+";
+
+/// The script making the failure tables of the HTML report sortable.
+const HTML_SCRIPT: &str = "\
+document.querySelectorAll('table.sortable th').forEach(function (th, column) {
+  th.addEventListener('click', function () {
+    var body = th.closest('table').tBodies[0];
+    var rows = Array.prototype.slice.call(body.rows);
+    var ascending = th.dataset.order !== 'asc';
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    rows.sort(function (a, b) {
+      var x = a.cells[column].textContent, y = b.cells[column].textContent;
+      return ascending ? x.localeCompare(y) : y.localeCompare(x);
+    });
+    rows.forEach(function (row) { body.appendChild(row); });
+  });
+});
+";
+
+/// Get the name of the host the operation is running on.
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+/// Describe the error of a file for the HTML report, with its new name if
+/// it was located under another name.
+fn html_error(file: &FileReport) -> String {
+    let error = file.error.as_ref().map(xml_escape).unwrap_or_default();
+    match &file.renamed {
+        Some(renamed) => format!(
+            "{} (probably renamed to {})",
+            error,
+            xml_escape(renamed.to_string_lossy())
+        ),
+        None => error,
+    }
+}
+
+/// A reporter writing a self-contained HTML page.
+///
+/// The page starts with the run metadata and the totals of the operation,
+/// followed by a section for each listing with a sortable table of the
+/// files that failed.
+struct HtmlReporter;
+
+impl Reporter for HtmlReporter {
+    fn buffered(&self) -> bool {
+        true
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        run: &Run,
+        files: &[FileReport],
+        totals: &Totals,
+    ) -> Result<(), IoError> {
+        let failures = totals.mismatch + totals.missing + totals.error;
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html>")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>cksfv.rs {} report</title>", run.operation)?;
+        writeln!(out, "<style>\n{}</style>", HTML_STYLE)?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+        writeln!(out, "<h1>cksfv.rs {} report</h1>", run.operation)?;
+
+        // run metadata
+        writeln!(out, "<h2>Summary</h2>")?;
+        writeln!(out, "<table>")?;
+        let metadata = [
+            ("Host", xml_escape(hostname())),
+            (
+                "Started",
+                run.started.to_rfc3339_opts(SecondsFormat::Secs, false),
+            ),
+            (
+                "Elapsed",
+                format!("{:.3}s", run.start.elapsed().as_secs_f64()),
+            ),
+            ("Version", crate_version!().to_string()),
+            ("Interrupted", run.interrupted.to_string()),
+            ("Files", totals.files.to_string()),
+            ("Bytes", totals.bytes.to_string()),
+            ("OK", totals.ok.to_string()),
+            ("Mismatch", totals.mismatch.to_string()),
+            ("Missing", totals.missing.to_string()),
+            ("Error", totals.error.to_string()),
+            ("Unlisted", totals.unlisted.to_string()),
+        ];
+        for (key, value) in metadata.iter() {
+            writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", key, value)?;
+        }
+        writeln!(out, "</table>")?;
+        if run.interrupted {
+            writeln!(out, "<p class=\"error\">Interrupted</p>")?;
+        }
+        if failures > 0 {
+            writeln!(out, "<p class=\"error\">{} file(s) failed</p>", failures)?;
+        } else if !run.interrupted {
+            writeln!(out, "<p class=\"ok\">Everything OK</p>")?;
+        }
+
+        // a section for each listing
+        for (listing, group) in group_by_listing(files) {
+            let title = match (listing, run.operation) {
+                (Some(path), _) => xml_escape(path.to_string_lossy()),
+                (None, Operation::Create) => String::from("Files"),
+                (None, Operation::Verify) => String::from("Unlisted files"),
+            };
+            let failed = group
+                .iter()
+                .filter(|f| f.status != Status::Ok)
+                .collect::<Vec<_>>();
+            writeln!(out, "<h2>{}</h2>", title)?;
+            writeln!(
+                out,
+                "<p>{} file(s), {} byte(s), {} not OK</p>",
+                group.len(),
+                group.iter().filter_map(|f| f.size).sum::<u64>(),
+                failed.len(),
+            )?;
+            if failed.is_empty() {
+                continue;
+            }
+            writeln!(out, "<table class=\"sortable\">")?;
+            writeln!(
+                out,
+                "<thead><tr><th>Path</th><th>Status</th><th>Expected</th><th>Actual</th><th>Error</th></tr></thead>"
+            )?;
+            writeln!(out, "<tbody>")?;
+            for file in failed {
+                writeln!(
+                    out,
+                    "<tr><td>{}</td><td class=\"{}\">{}</td><td class=\"mono\">{}</td><td class=\"mono\">{}</td><td>{}</td></tr>",
+                    xml_escape(file.path.to_string_lossy()),
+                    file.status,
+                    file.status,
+                    file.expected.map(|c| format!("{:08X}", c)).unwrap_or_default(),
+                    file.actual.map(|c| format!("{:08X}", c)).unwrap_or_default(),
+                    html_error(file),
+                )?;
+            }
+            writeln!(out, "</tbody>")?;
+            writeln!(out, "</table>")?;
+        }
+
+        writeln!(out, "<script>\n{}</script>", HTML_SCRIPT)?;
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write an HTML report for a run without any result.
+    fn html(interrupted: bool) -> String {
+        let run = Run {
+            operation: Operation::Verify,
+            started: Local::now(),
+            start: Instant::now(),
+            interrupted,
+        };
+        let mut out = Vec::new();
+        HtmlReporter
+            .finish(&mut out, &run, &[], &Totals::default())
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn html_interrupted() {
+        let page = html(true);
+        assert!(page.contains("<p class=\"error\">Interrupted</p>"));
+        assert!(!page.contains("Everything OK"));
+    }
+
+    #[test]
+    fn html_complete() {
+        let page = html(false);
+        assert!(!page.contains("Interrupted</p>"));
+        assert!(page.contains("<p class=\"ok\">Everything OK</p>"));
+    }
+}
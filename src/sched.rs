@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io::Error as IoError;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::compute_crc32;
+use super::progress::Progress;
+use super::CancellationToken;
+use super::Config;
+use super::Crc32;
+
+/// The delay between two refreshes of the progress line.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A queue of files located on the same device, with their submission index.
+type DeviceQueue = Arc<Mutex<VecDeque<(usize, PathBuf)>>>;
+
+/// The result of hashing a file, with the time spent doing so.
+type Timed = (Result<Crc32, IoError>, Duration);
+
+/// Get an identifier for the device a file is located on, and its size.
+#[cfg(unix)]
+fn stat(path: &Path) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path)
+        .map(|m| (m.dev(), m.len()))
+        .unwrap_or((0, 0))
+}
+
+/// Get an identifier for the device a file is located on, and its size.
+#[cfg(not(unix))]
+fn stat(path: &Path) -> (u64, u64) {
+    std::fs::metadata(path)
+        .map(|m| (0, m.len()))
+        .unwrap_or((0, 0))
+}
+
+/// The results of hashing several files, yielded in submission order.
+///
+/// Files are hashed in background threads, so the results of files located
+/// on different devices may become available out of order: they are buffered
+/// until all the results of the files submitted before them have been yielded.
+/// Dropping the iterator, or cancelling the token of the configuration,
+/// makes the workers stop after their current file.
+///
+/// If a worker stops without cancellation, e.g. because it panicked, the
+/// files it did not hash are yielded as errors instead of being skipped.
+///
+/// When progress reporting is enabled, a progress line is drawn on the
+/// terminal while waiting for results, and erased before yielding them.
+pub(crate) struct HashResults {
+    receiver: Receiver<(usize, Timed)>,
+    pending: BTreeMap<usize, Timed>,
+    next: usize,
+    total: usize,
+    elapsed: Duration,
+    progress: Option<Arc<Progress>>,
+    drawn: bool,
+    cancellation: CancellationToken,
+}
+
+impl HashResults {
+    /// Get the time spent hashing the file of the last yielded result.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Receive the next available result, redrawing the progress if needed.
+    fn recv(&mut self) -> Option<(usize, Timed)> {
+        let progress = match &self.progress {
+            Some(progress) => progress,
+            None => return self.receiver.recv().ok(),
+        };
+        loop {
+            match self.receiver.recv_timeout(PROGRESS_INTERVAL) {
+                Ok(message) => return Some(message),
+                Err(RecvTimeoutError::Disconnected) => return None,
+                Err(RecvTimeoutError::Timeout) => {
+                    progress.draw(&mut std::io::stderr());
+                    self.drawn = true;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for HashResults {
+    type Item = Result<Crc32, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.total {
+            return None;
+        }
+        while !self.pending.contains_key(&self.next) {
+            match self.recv() {
+                Some((index, result)) => self.pending.insert(index, result),
+                None if self.cancellation.is_cancelled() => return None,
+                None => {
+                    let err = IoError::other("hashing worker stopped unexpectedly");
+                    self.pending.insert(self.next, (Err(err), Duration::ZERO))
+                }
+            };
+        }
+        if let Some(progress) = self.progress.as_ref().filter(|_| self.drawn) {
+            progress.clear(&mut std::io::stderr());
+            self.drawn = false;
+        }
+        let (result, elapsed) = self.pending.remove(&self.next)?;
+        self.elapsed = elapsed;
+        self.next += 1;
+        Some(result)
+    }
+}
+
+/// Hash the given files, reading files on different devices in parallel.
+///
+/// Files are grouped by the device they are located on, and each device is
+/// assigned `config.jobs` worker threads, so that spinning disks are read
+/// sequentially while several disks can still be read at the same time.
+pub(crate) fn hash_files<I>(files: I, config: &Config) -> HashResults
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    // group the files by device, preserving their submission order
+    let mut queues: HashMap<u64, VecDeque<(usize, PathBuf)>> = HashMap::new();
+    let mut total = 0;
+    let mut size = 0;
+    for (index, file) in files.into_iter().enumerate() {
+        let (device, len) = stat(&file);
+        queues.entry(device).or_default().push_back((index, file));
+        total += 1;
+        size += len;
+    }
+    let progress = if config.progress {
+        Some(Arc::new(Progress::new(size)))
+    } else {
+        None
+    };
+
+    // spawn the workers for each device
+    let (sender, receiver) = std::sync::mpsc::channel();
+    for queue in queues.into_values() {
+        let workers = config.jobs.max(1).min(queue.len());
+        let queue: DeviceQueue = Arc::new(Mutex::new(queue));
+        for _ in 0..workers {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            let config = config.clone();
+            let progress = progress.clone();
+            std::thread::spawn(move || loop {
+                if config.cancellation.is_cancelled() {
+                    break;
+                }
+                let next = queue.lock().unwrap().pop_front();
+                let (index, file) = match next {
+                    Some(job) => job,
+                    None => break,
+                };
+                let start = Instant::now();
+                let result = compute_crc32(&file, &config, progress.as_deref());
+                if sender.send((index, (result, start.elapsed()))).is_err() {
+                    break;
+                }
+            });
+        }
+    }
+
+    HashResults {
+        receiver,
+        pending: BTreeMap::new(),
+        next: 0,
+        total,
+        elapsed: Duration::ZERO,
+        progress,
+        drawn: false,
+        cancellation: config.cancellation.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the results of two files, only the second of which was hashed.
+    fn lost_worker(cancellation: CancellationToken) -> HashResults {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        sender.send((1, (Ok(1), Duration::ZERO))).unwrap();
+        HashResults {
+            receiver,
+            pending: BTreeMap::new(),
+            next: 0,
+            total: 2,
+            elapsed: Duration::ZERO,
+            progress: None,
+            drawn: false,
+            cancellation,
+        }
+    }
+
+    #[test]
+    fn lost_worker_is_an_error() {
+        let results = lost_worker(CancellationToken::new()).collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_err());
+        assert_eq!(results[1].as_ref().unwrap(), &1);
+    }
+
+    #[test]
+    fn cancelled_stops() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        assert_eq!(lost_worker(cancellation).count(), 0);
+    }
+}
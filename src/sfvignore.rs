@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+use ignore::Error as IgnoreError;
+use ignore::Match;
+
+/// The name of the files listing the paths to ignore in a directory.
+pub const IGNORE_FILE: &str = ".sfvignore";
+
+/// The `.sfvignore` files found while traversing a directory tree.
+///
+/// Each file uses the gitignore syntax, and applies to the directory it is
+/// in and to all of its subdirectories, with the patterns of the deepest
+/// file taking precedence.
+#[derive(Debug)]
+pub(crate) struct IgnoreFiles {
+    root: PathBuf,
+    cache: HashMap<PathBuf, Option<Gitignore>>,
+    errors: Vec<(PathBuf, IgnoreError)>,
+}
+
+impl IgnoreFiles {
+    /// Create a new set of ignore files for the tree under `root`.
+    pub fn new(root: &Path) -> Self {
+        IgnoreFiles {
+            root: root.to_path_buf(),
+            cache: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Check whether the given path found under the root is ignored.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+        let root = self.root.clone();
+        for dir in parent.ancestors().take_while(|d| d.starts_with(&root)) {
+            if let Some(gitignore) = self.load(dir) {
+                match gitignore.matched(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => (),
+                }
+            }
+        }
+        false
+    }
+
+    /// Take the errors encountered while reading the ignore files.
+    pub fn take_errors(&mut self) -> Vec<(PathBuf, IgnoreError)> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Get the patterns of the ignore file in the given directory, if any.
+    fn load(&mut self, dir: &Path) -> Option<&Gitignore> {
+        if !self.cache.contains_key(dir) {
+            let path = dir.join(IGNORE_FILE);
+            let gitignore = if path.is_file() {
+                let mut builder = GitignoreBuilder::new(dir);
+                if let Some(err) = builder.add(&path) {
+                    self.errors.push((path.clone(), err));
+                }
+                match builder.build() {
+                    Ok(gitignore) => Some(gitignore),
+                    Err(err) => {
+                        self.errors.push((path, err));
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            self.cache.insert(dir.to_path_buf(), gitignore);
+        }
+        self.cache[dir].as_ref()
+    }
+}
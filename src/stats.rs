@@ -0,0 +1,146 @@
+use std::io::Error as IoError;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::progress::format_size;
+use super::report::FileReport;
+use super::report::Status;
+
+/// The number of slowest files listed in a summary.
+const SLOWEST: usize = 5;
+
+/// Statistics about the files checked during a verification run.
+#[derive(Debug)]
+pub(crate) struct Stats {
+    start: Option<Instant>,
+    files: usize,
+    bytes: u64,
+    ok: usize,
+    mismatch: usize,
+    missing: usize,
+    error: usize,
+    hashing: Duration,
+    slowest: Vec<(Duration, PathBuf)>,
+}
+
+impl Stats {
+    /// Start collecting statistics, measuring the elapsed time from now.
+    pub fn new() -> Self {
+        Stats {
+            start: Some(Instant::now()),
+            ..Stats::subtotal()
+        }
+    }
+
+    /// Start collecting statistics for a part of a run.
+    ///
+    /// The files of the other parts are hashed at the same time, so the time
+    /// of a part is the sum of the time spent hashing each of its files
+    /// rather than the elapsed time.
+    pub fn subtotal() -> Self {
+        Stats {
+            start: None,
+            files: 0,
+            bytes: 0,
+            ok: 0,
+            mismatch: 0,
+            missing: 0,
+            error: 0,
+            hashing: Duration::ZERO,
+            slowest: Vec::new(),
+        }
+    }
+
+    /// Record the result of checking a file.
+    pub fn record(&mut self, file: &FileReport) {
+        self.files += 1;
+        self.bytes += file.size.unwrap_or(0);
+        match file.status {
+            Status::Ok => self.ok += 1,
+            Status::Mismatch => self.mismatch += 1,
+            Status::Missing => self.missing += 1,
+            Status::Error => self.error += 1,
+            Status::Unlisted => (),
+        }
+        if let Some(elapsed) = file.elapsed {
+            self.hashing += elapsed;
+            self.slowest.push((elapsed, file.path.clone()));
+            self.trim();
+        }
+    }
+
+    /// Add the statistics of another run to these ones.
+    ///
+    /// The paths of the files of `other` are made relative to `prefix`.
+    pub fn merge(&mut self, other: &Stats, prefix: &Path) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+        self.ok += other.ok;
+        self.mismatch += other.mismatch;
+        self.missing += other.missing;
+        self.error += other.error;
+        self.hashing += other.hashing;
+        let slowest = other.slowest.iter().map(|(e, p)| (*e, prefix.join(p)));
+        self.slowest.extend(slowest);
+        self.trim();
+    }
+
+    /// Only keep the slowest files, slowest first.
+    fn trim(&mut self) {
+        self.slowest
+            .sort_by_key(|(elapsed, _)| std::cmp::Reverse(*elapsed));
+        self.slowest.truncate(SLOWEST);
+    }
+
+    /// Write a summary of the statistics under the given title.
+    ///
+    /// The throughput is computed from the elapsed time for a whole run, and
+    /// from the hashing time for a part of a run.
+    pub fn write<W: Write + ?Sized>(&self, out: &mut W, title: &str) -> Result<(), IoError> {
+        writeln!(out, "{}:", title)?;
+        writeln!(
+            out,
+            "  Files checked:  {} ({} OK, {} different CRC, {} missing, {} errors)",
+            self.files, self.ok, self.mismatch, self.missing, self.error
+        )?;
+        writeln!(
+            out,
+            "  Total bytes:    {} ({})",
+            self.bytes,
+            format_size(self.bytes as f64)
+        )?;
+        let elapsed = match self.start {
+            Some(start) => {
+                let elapsed = start.elapsed().as_secs_f64();
+                writeln!(out, "  Elapsed time:   {:.3}s", elapsed)?;
+                elapsed
+            }
+            None => {
+                let hashing = self.hashing.as_secs_f64();
+                writeln!(out, "  Hashing time:   {:.3}s", hashing)?;
+                hashing
+            }
+        };
+        let rate = if elapsed > 0.0 {
+            self.bytes as f64 / elapsed
+        } else {
+            0.0
+        };
+        writeln!(out, "  Throughput:     {}/s", format_size(rate))?;
+        if !self.slowest.is_empty() {
+            writeln!(out, "  Slowest files:")?;
+            for (elapsed, path) in self.slowest.iter() {
+                writeln!(
+                    out,
+                    "    {:>9.3}s  {}",
+                    elapsed.as_secs_f64(),
+                    path.display()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
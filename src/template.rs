@@ -0,0 +1,212 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::DateTime;
+use chrono::Local;
+
+use super::report::FileReport;
+
+/// A part of an output line template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    /// Text copied as is.
+    Literal(String),
+    /// `%p`: the path of the file, as listed.
+    Path,
+    /// `%b`: the basename of the file.
+    Basename,
+    /// `%e`: the expected CRC, if any.
+    Expected,
+    /// `%c`: the actual CRC, if the file could be read.
+    Actual,
+    /// `%s`: the size of the file in bytes.
+    Size,
+    /// `%S`: the status of the file.
+    Status,
+    /// `%m`: the modification time of the file.
+    Mtime,
+}
+
+/// A template for the lines written for each file, in the style of `printf`.
+///
+/// The following placeholders are supported: `%p` for the path, `%b` for
+/// the basename, `%e` and `%c` for the expected and actual CRC, `%s` for
+/// the size, `%S` for the status and `%m` for the modification time. `%%`
+/// gives a literal `%`, and `\n`, `\t` and `\\` are unescaped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Render the line of a file, read from the given location.
+    ///
+    /// The metadata of the file is only read if a placeholder needs it.
+    pub(crate) fn render(&self, report: &FileReport, file: &Path) -> String {
+        let crc32 = |c: Option<u32>| c.map(|c| format!("{:08X}", c)).unwrap_or_default();
+        let needs_metadata = self.parts.iter().any(|part| match part {
+            Part::Size => report.size.is_none(),
+            Part::Mtime => true,
+            _ => false,
+        });
+        let metadata = Some(file)
+            .filter(|_| needs_metadata)
+            .and_then(|file| std::fs::metadata(file).ok());
+        let mut line = String::new();
+        for part in self.parts.iter() {
+            match part {
+                Part::Literal(text) => line.push_str(text),
+                Part::Path => line.push_str(&report.path.to_string_lossy()),
+                Part::Basename => {
+                    let name = report.path.file_name().unwrap_or(report.path.as_os_str());
+                    line.push_str(&name.to_string_lossy())
+                }
+                Part::Expected => line.push_str(&crc32(report.expected)),
+                Part::Actual => line.push_str(&crc32(report.actual)),
+                Part::Size => {
+                    let size = report.size.or(metadata.as_ref().map(|m| m.len()));
+                    line.push_str(&size.map(|s| s.to_string()).unwrap_or_default())
+                }
+                Part::Status => line.push_str(&report.status.to_string()),
+                Part::Mtime => {
+                    let mtime = metadata.as_ref().and_then(|m| m.modified().ok());
+                    if let Some(mtime) = mtime.map(DateTime::<Local>::from) {
+                        line.push_str(&mtime.format("%Y-%m-%d %H:%M:%S").to_string())
+                    }
+                }
+            }
+        }
+        line
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            let part = match c {
+                '%' => match chars.next() {
+                    Some('%') => {
+                        literal.push('%');
+                        continue;
+                    }
+                    Some('p') => Part::Path,
+                    Some('b') => Part::Basename,
+                    Some('e') => Part::Expected,
+                    Some('c') => Part::Actual,
+                    Some('s') => Part::Size,
+                    Some('S') => Part::Status,
+                    Some('m') => Part::Mtime,
+                    Some(other) => return Err(format!("invalid placeholder: %{}", other)),
+                    None => return Err(String::from("invalid placeholder: %")),
+                },
+                '\\' => {
+                    match chars.next() {
+                        Some('n') => literal.push('\n'),
+                        Some('t') => literal.push('\t'),
+                        Some('\\') => literal.push('\\'),
+                        Some(other) => {
+                            literal.push('\\');
+                            literal.push(other);
+                        }
+                        None => literal.push('\\'),
+                    }
+                    continue;
+                }
+                c => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template {
+            source: s.to_string(),
+            parts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a template and get its parts.
+    fn parts(s: &str) -> Vec<Part> {
+        s.parse::<Template>().unwrap().parts
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            parts("%p: %c (%S)"),
+            [
+                Part::Path,
+                Part::Literal(String::from(": ")),
+                Part::Actual,
+                Part::Literal(String::from(" (")),
+                Part::Status,
+                Part::Literal(String::from(")")),
+            ]
+        );
+        assert_eq!(parts("%m"), [Part::Mtime]);
+        assert_eq!(
+            parts("%b%e%s"),
+            [Part::Basename, Part::Expected, Part::Size]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(parts("100%%"), [Part::Literal(String::from("100%"))]);
+        assert_eq!(parts("%%p"), [Part::Literal(String::from("%p"))]);
+        assert_eq!(parts("a\\nb"), [Part::Literal(String::from("a\nb"))]);
+        assert_eq!(parts("a\\tb"), [Part::Literal(String::from("a\tb"))]);
+        assert_eq!(parts("a\\\\b"), [Part::Literal(String::from("a\\b"))]);
+        assert_eq!(parts("a\\xb"), [Part::Literal(String::from("a\\xb"))]);
+        assert_eq!(parts("a\\"), [Part::Literal(String::from("a\\"))]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("%".parse::<Template>().is_err());
+        assert!("%p %".parse::<Template>().is_err());
+        assert!("%x".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn render() {
+        let file = Path::new("Cargo.toml");
+        let size = std::fs::metadata(file).unwrap().len();
+        let report = FileReport::unlisted(Path::new("./Cargo.toml"), file);
+        let template = "%b %s %S%e".parse::<Template>().unwrap();
+        let line = template.render(&report, file);
+        assert_eq!(line, format!("Cargo.toml {} unlisted", size));
+    }
+
+    #[test]
+    fn display() {
+        let template = "%p\\t%c".parse::<Template>().unwrap();
+        assert_eq!(template.to_string(), "%p\\t%c");
+    }
+}
@@ -1,4 +1,5 @@
 extern crate assert_cli;
+extern crate tempfile;
 #[macro_use]
 extern crate textwrap_macros;
 
@@ -16,6 +17,18 @@ fn data(name: &str) -> String {
         .expect("invalid Unicode data")
 }
 
+/// Get an assertion running the compiled binary directly.
+///
+/// Unlike `Assert::main_binary`, this does not go through `cargo run`, so the
+/// command can be run from any working directory. The configuration folder
+/// is redirected to a missing directory so that a backend saved by the user
+/// cannot leak into the tests.
+fn binary() -> assert_cli::Assert {
+    let config = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config");
+    let env = assert_cli::Environment::inherit().insert("XDG_CONFIG_HOME", config);
+    assert_cli::Assert::command(&[env!("CARGO_BIN_EXE_cksfv")]).with_env(env)
+}
+
 /// Tests to mimick the original behaviour of `cksfv`.
 mod behaviour {
 
@@ -307,3 +320,1179 @@ mod io {
             .unwrap()
     }
 }
+
+/// Tests for options not available in the original `cksfv`.
+mod extensions {
+
+    use super::binary;
+    use super::data;
+
+    #[test]
+    /// Check that `--bwlimit` still produces the right checksums.
+    fn bwlimit() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--bwlimit", "1k", "-b", &data("1.txt")])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("1.txt 582507A1")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--jobs` reports results in the order of the listing.
+    fn jobs_ordered() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--jobs", "4", "-c", "-g", &data("12.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .is(dedent!(
+                r#"
+                    1.txt                                             OK
+                    2.txt                                             OK
+                    Everything OK
+                    "#
+            )
+            .trim())
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that no progress is displayed when stderr is not a terminal.
+    fn progress_not_a_terminal() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["-g", &data("12.sfv")])
+            .succeeds()
+            .and()
+            .stderr()
+            .doesnt_contain("ETA")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `-r` fails when one of the listings has errors.
+    fn recursive_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("1.sfv"), "1.txt 00000000\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r"])
+            .fails()
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `-C` sets the directory checked recursively.
+    fn recursive_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("sub").join("1.sfv"), "1.txt 00000000\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(dir.path().join("2.sfv"), "2.txt 3625A74A\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "-C", "sub"])
+            .fails()
+            .and()
+            .stderr()
+            .doesnt_contain("2.sfv")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `-r` with explicit files lists them instead of ignoring them.
+    fn recursive_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("1.sfv"), "1.txt 00000000\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "1.txt"])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("\n1.txt 582507A1\n")
+            .and()
+            .stderr()
+            .doesnt_contain("Verifying")
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    /// Verify three slowly read files with `args`, interrupting with `SIGINT`
+    /// once `started` is written, and get the exit code and full output.
+    fn interrupt(args: &[&str], started: &str) -> (Option<i32>, String) {
+        use std::io::BufRead;
+        use std::io::BufReader;
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut listing = String::new();
+        for name in ["a.bin", "b.bin", "c.bin"] {
+            std::fs::write(dir.path().join(name), vec![0; 1 << 16]).unwrap();
+            listing.push_str(&format!("{} D7978EEB\n", name));
+        }
+        std::fs::write(dir.path().join("all.sfv"), listing).unwrap();
+
+        let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_cksfv"))
+            .current_dir(dir.path())
+            .args(["--bwlimit", "64k", "-f", "all.sfv"])
+            .args(args)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+
+        // wait for the first file to be checked before interrupting
+        let mut stdout = String::new();
+        let mut reader = BufReader::new(child.stdout.take().unwrap());
+        while !stdout.contains(started) {
+            assert_ne!(reader.read_line(&mut stdout).unwrap(), 0);
+        }
+        std::process::Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .unwrap();
+
+        reader.read_to_string(&mut stdout).unwrap();
+        (child.wait().unwrap().code(), stdout)
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Check that `SIGINT` stops the verification with a partial summary.
+    fn interrupted() {
+        let (code, stdout) = interrupt(&["-c"], "a.bin");
+        assert_eq!(code, Some(130));
+        assert!(stdout.contains("Interrupted after checking"));
+        assert!(stdout.ends_with("Errors Occured\n"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Check that `SIGINT` closes the pending `file_start` event in NDJSON.
+    fn interrupted_ndjson() {
+        let (code, stdout) = interrupt(&["--format", "ndjson"], "file_result");
+        assert_eq!(code, Some(130));
+        let count = |event: &str| stdout.matches(event).count();
+        assert_eq!(
+            count("file_start"),
+            count("file_result") + count("file_cancel")
+        );
+        assert_eq!(count("file_cancel"), 1);
+        let last = stdout.lines().last().unwrap();
+        assert!(last.contains(r#""interrupted": true"#));
+    }
+
+    #[test]
+    /// Check that `--backend` produces the same checksums as the default.
+    fn backend_parallel() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--backend", "parallel:2", "-b", &data("1.txt")])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("1.txt 582507A1")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--benchmark --save` stores the fastest backend.
+    fn benchmark_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = assert_cli::Environment::inherit().insert("XDG_CONFIG_HOME", dir.path());
+        assert_cli::Assert::main_binary()
+            .with_env(&env)
+            .with_args(&["--benchmark", &data("1.txt"), "--save"])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("Fastest backend for 4 B:")
+            .unwrap();
+
+        let saved = dir.path().join("cksfv.rs").join("backend");
+        assert!(saved.exists());
+        assert_cli::Assert::main_binary()
+            .with_env(&env)
+            .with_args(&["-g", &data("1.sfv")])
+            .succeeds()
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `-r` lists the content of directories when generating.
+    fn recursive_newsfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("sub").join("2.txt"), "Two\n").unwrap();
+        std::fs::write(dir.path().join("sub").join("2.sfv"), "2.txt 3625A74A\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "."])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("./1.txt 582507A1\n./sub/2.txt 3625A74A")
+            .and()
+            .stdout()
+            .doesnt_contain("2.sfv")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--include` and `--exclude` filter recursive generation.
+    fn filter_newsfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("1.nfo"), "Info\n").unwrap();
+        std::fs::write(dir.path().join("Thumbs.db"), "").unwrap();
+        std::fs::write(dir.path().join(".git").join("2.txt"), "Two\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", ".", "--exclude", ".git", "--exclude", "*.nfo"])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("./Thumbs.db 00000000")
+            .and()
+            .stdout()
+            .doesnt_contain("2.txt")
+            .and()
+            .stdout()
+            .doesnt_contain("1.nfo")
+            .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", ".", "--include", "*.txt", "--exclude", ".git"])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("./1.txt 582507A1")
+            .and()
+            .stdout()
+            .doesnt_contain("Thumbs.db")
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `.sfvignore` files are inherited by subdirectories.
+    fn sfvignore() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+        std::fs::write(dir.path().join(".sfvignore"), "*.nfo\n").unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("a").join("1.nfo"), "Info\n").unwrap();
+        std::fs::write(
+            dir.path().join("a").join("b").join(".sfvignore"),
+            "!keep.nfo\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("a").join("b").join("keep.nfo"), "").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "."])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("./1.txt 582507A1\n./a/b/keep.nfo 00000000")
+            .and()
+            .stdout()
+            .doesnt_contain("1.nfo")
+            .and()
+            .stdout()
+            .doesnt_contain(".sfvignore ")
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--exclude` skips the entries of a listing when verifying.
+    fn filter_cksfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "1.txt 582507A1\n1.nfo 00000000\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("sub").join("bad.sfv"), "2.txt 00000000\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-g", "list.sfv", "--exclude", "*.nfo"])
+            .succeeds()
+            .and()
+            .stderr()
+            .doesnt_contain("1.nfo")
+            .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "--exclude", "*.nfo", "--exclude", "sub"])
+            .succeeds()
+            .and()
+            .stderr()
+            .doesnt_contain("bad.sfv")
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that recursive verification matches filters from the walk root.
+    fn filter_cksfv_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("b.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("sub").join("a.nfo"), "Two\n").unwrap();
+        std::fs::write(
+            dir.path().join("sub").join("l.sfv"),
+            "a.nfo 00000000\nb.txt 582507A1\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "--exclude", "sub/*.nfo"])
+            .succeeds()
+            .and()
+            .stderr()
+            .doesnt_contain("a.nfo")
+            .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "--include", "sub/*.txt"])
+            .succeeds()
+            .and()
+            .stderr()
+            .contains("b.txt")
+            .and()
+            .stderr()
+            .doesnt_contain("a.nfo")
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--per-dir` writes a listing in each directory.
+    fn per_dir_newsfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("sub").join("2.txt"), "Two\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--per-dir", "files.sfv"])
+            .succeeds()
+            .unwrap();
+
+        let root = std::fs::read_to_string(dir.path().join("files.sfv")).unwrap();
+        assert!(root.ends_with("\n1.txt 582507A1\n"));
+        let sub = std::fs::read_to_string(dir.path().join("sub").join("files.sfv")).unwrap();
+        assert!(sub.ends_with("\n2.txt 3625A74A\n"));
+        let header = sub.lines().find(|l| l.ends_with(".txt")).unwrap();
+        assert!(header.starts_with(";"));
+        assert!(header.ends_with(" 2.txt"));
+        assert!(!header.contains("sub"));
+    }
+
+    #[test]
+    /// Check that `--update` only adds the files missing from the listing.
+    fn update() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(dir.path().join("list.sfv"), "; header\n1.txt 582507A1\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--update", "list.sfv"])
+            .succeeds()
+            .and()
+            .stdout()
+            .is("2.txt 3625A74A")
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("list.sfv")).unwrap();
+        let mut lines = listing.lines();
+        assert_eq!(lines.next(), Some("; header"));
+        assert!(lines.next().unwrap().ends_with(" 2.txt"));
+        assert_eq!(lines.next(), Some("1.txt 582507A1"));
+        assert_eq!(lines.next(), Some("2.txt 3625A74A"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    /// Check that `--update` recognizes entries written with a `./` prefix.
+    fn update_curdir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("list.sfv"), "./1.txt 582507A1\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--update", "list.sfv"])
+            .succeeds()
+            .and()
+            .stdout()
+            .is("")
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("list.sfv")).unwrap();
+        assert_eq!(listing, "./1.txt 582507A1\n");
+    }
+
+    #[test]
+    /// Check that `--update` lists explicit files relative to the listing.
+    fn update_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("sub").join("list.sfv"), "; header\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--update", "sub/list.sfv", "sub/1.txt"])
+            .succeeds()
+            .and()
+            .stdout()
+            .is("1.txt 582507A1")
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("sub").join("list.sfv")).unwrap();
+        assert!(listing.ends_with("\n1.txt 582507A1\n"));
+    }
+
+    #[test]
+    /// Check that `--prune` only removes the entries of missing files.
+    fn prune() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Changed\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "; header\n1.txt 582507A1\n2.txt 3625A74A\n3.txt 00000000\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--prune", "list.sfv"])
+            .succeeds()
+            .and()
+            .stdout()
+            .is("3.txt 00000000")
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("list.sfv")).unwrap();
+        assert_eq!(listing, "; header\n1.txt 582507A1\n2.txt 3625A74A\n");
+    }
+
+    #[test]
+    /// Check that `--fix --yes` updates every mismatched entry in place.
+    fn fix_all() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "; header\n1.txt 582507A1\n2.txt 00000000\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--fix", "list.sfv", "--yes"])
+            .succeeds()
+            .and()
+            .stdout()
+            .is("2.txt 3625A74A")
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("list.sfv")).unwrap();
+        assert_eq!(listing, "; header\n1.txt 582507A1\n2.txt 3625A74A\n");
+    }
+
+    #[test]
+    /// Check that `--fix` asks before updating an entry.
+    fn fix_interactive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "1.txt 00000000\n2.txt 00000000\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--fix", "list.sfv", "--output", "new.sfv"])
+            .stdin("n\ny\n")
+            .succeeds()
+            .and()
+            .stdout()
+            .is("2.txt 3625A74A")
+            .and()
+            .stderr()
+            .contains("1.txt: 00000000 -> 582507A1, update? [y/N]")
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("list.sfv")).unwrap();
+        assert_eq!(listing, "1.txt 00000000\n2.txt 00000000\n");
+        let listing = std::fs::read_to_string(dir.path().join("new.sfv")).unwrap();
+        assert_eq!(listing, "1.txt 00000000\n2.txt 3625A74A\n");
+    }
+
+    #[test]
+    /// Check that `--diff` reports added, removed, changed and renamed entries.
+    fn diff() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("old.sfv"),
+            "; old\n1.txt 582507A1\n2.txt 3625A74A\n3.txt 00000000\n4.txt 11111111\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("new.sfv"),
+            "; new\n1.txt 582507A1\n2.txt 12345678\n5.txt 00000000\n6.txt 22222222\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--diff", "old.sfv", "new.sfv"])
+            .fails()
+            .and()
+            .stdout()
+            .is(textwrap_macros::dedent!(
+                r#"
+                changed  2.txt 3625A74A -> 12345678
+                removed  3.txt 00000000
+                removed  4.txt 11111111
+                added    5.txt 00000000
+                added    6.txt 22222222
+                "#
+            )
+            .trim())
+            .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--diff", "old.sfv", "new.sfv", "--renames", "--porcelain"])
+            .fails()
+            .and()
+            .stdout()
+            .is("M\t2.txt\t3625A74A\t12345678\nR\t3.txt\t5.txt\t00000000\nD\t4.txt\t11111111\nA\t6.txt\t22222222")
+            .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--diff", "old.sfv", "old.sfv"])
+            .succeeds()
+            .and()
+            .stdout()
+            .is("")
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--merge --rebase` combines listings and reports conflicts.
+    fn merge() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("cd1")).unwrap();
+        std::fs::create_dir(dir.path().join("cd2")).unwrap();
+        std::fs::write(dir.path().join("cd1/cd1.sfv"), "; cd1\n1.txt 582507A1\n").unwrap();
+        std::fs::write(
+            dir.path().join("cd2/cd2.sfv"),
+            "2.txt 3625A74A\n../cd1/1.txt 582507A1\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("extra.sfv"), "cd2/2.txt 12345678\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&[
+                "--merge",
+                "cd1/cd1.sfv",
+                "cd2/cd2.sfv",
+                "--rebase",
+                "--output",
+                "all.sfv",
+            ])
+            .succeeds()
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("all.sfv")).unwrap();
+        let entries = listing
+            .lines()
+            .filter(|l| !l.starts_with(';'))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, ["cd1/1.txt 582507A1", "cd2/2.txt 3625A74A"]);
+
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--merge", "all.sfv", "extra.sfv"])
+            .fails()
+            .and()
+            .stderr()
+            .is("cksfv: cd2/2.txt: conflicting CRC 3625A74A in all.sfv and 12345678 in extra.sfv")
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--rebase` reaches files outside the merged listing folder.
+    fn merge_sibling() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("cd1")).unwrap();
+        std::fs::create_dir(dir.path().join("cd2")).unwrap();
+        std::fs::write(dir.path().join("cd1/cd1.sfv"), "1.txt 582507A1\n").unwrap();
+        std::fs::write(dir.path().join("cd2/cd2.sfv"), "2.txt 3625A74A\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&[
+                "--merge",
+                "cd1/cd1.sfv",
+                "cd2/cd2.sfv",
+                "--rebase",
+                "--output",
+                "cd2/all.sfv",
+            ])
+            .succeeds()
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("cd2/all.sfv")).unwrap();
+        let entries = listing
+            .lines()
+            .filter(|l| !l.starts_with(';'))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, ["../cd1/1.txt 582507A1", "2.txt 3625A74A"]);
+    }
+
+    #[test]
+    /// Check that `--locate --rewrite` finds and records renamed files.
+    fn locate() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("one.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            ";            4  12:00.00 2020-01-01 1.txt\n1.txt 582507A1\n2.txt 3625A74A\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-g", "list.sfv", "--locate", "--rewrite"])
+            .fails()
+            .and()
+            .stderr()
+            .contains("1.txt                                             missing, probably renamed to sub/one.txt")
+            .unwrap();
+
+        let listing = std::fs::read_to_string(dir.path().join("list.sfv")).unwrap();
+        assert!(listing.ends_with("\nsub/one.txt 582507A1\n2.txt 3625A74A\n"));
+    }
+
+    #[test]
+    /// Check that `--locate` ignores the files listed by other listings.
+    fn locate_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("one.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("sub").join("sub.sfv"), "one.txt 582507A1\n").unwrap();
+        std::fs::write(dir.path().join("list.sfv"), "1.txt 582507A1\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-r", "--locate"])
+            .fails()
+            .and()
+            .stdout()
+            .doesnt_contain("probably renamed")
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--locate` records the new names in machine formats.
+    fn locate_json() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("one.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("list.sfv"), "1.txt 582507A1\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--format", "json", "-g", "list.sfv", "--locate"])
+            .fails()
+            .and()
+            .stdout()
+            .contains(r#""status": "missing""#)
+            .and()
+            .stdout()
+            .contains(r#""renamed": "sub/one.txt""#)
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--format json` writes a single document when verifying.
+    fn json_cksfv() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--format", "json", "-g", &data("12.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains(r#""operation": "verify""#)
+            .and()
+            .stdout()
+            .contains(r#"{"listing": "tests/data/12.sfv", "path": "1.txt", "expected": "582507A1", "actual": "582507A1", "status": "ok", "size": 4, "error": null}"#)
+            .and()
+            .stdout()
+            .contains(r#""totals": {"files": 2, "ok": 2, "mismatch": 0, "missing": 0, "error": 0, "unlisted": 0, "bytes": 8}"#)
+            .and()
+            .stderr()
+            .is("")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format json` reports files that could not be hashed.
+    fn json_newsfv() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--format", "json", &data("1.txt"), "missing.txt"])
+            .fails()
+            .and()
+            .stdout()
+            .contains(r#""operation": "create""#)
+            .and()
+            .stdout()
+            .contains(r#""path": "missing.txt", "expected": null, "actual": null, "status": "missing", "size": null"#)
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format ndjson` writes one event per line in order.
+    fn ndjson_cksfv() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--format", "ndjson", "-g", &data("1.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .satisfies(
+                |out| {
+                    let events = out
+                        .lines()
+                        .map(|l| l.split('"').nth(3).unwrap())
+                        .collect::<Vec<_>>();
+                    events == ["run_start", "file_start", "file_result", "run_end"]
+                },
+                "unexpected events",
+            )
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format junit` writes a test suite for each listing.
+    fn junit_cksfv() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--format", "junit", "-g", &data("12.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains(r#"<testsuite name="tests/data/12.sfv" tests="2" failures="0" errors="0" skipped="0""#)
+            .and()
+            .stdout()
+            .contains(r#"<testcase classname="tests/data/12.sfv" name="2.txt"/>"#)
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format junit` reports mismatched and missing files as failures.
+    fn junit_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "1.txt 00000000\n<2>.txt 3625A74A\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--format", "junit", "-g", "list.sfv"])
+            .fails()
+            .and()
+            .stdout()
+            .contains(r#"failures="2" errors="0""#)
+            .and()
+            .stdout()
+            .contains(r#"<failure type="mismatch" message="different CRC">expected: 00000000, actual: 582507A1</failure>"#)
+            .and()
+            .stdout()
+            .contains(r#"<testcase classname="list.sfv" name="&lt;2&gt;.txt">"#)
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format tap` writes a test point for each entry.
+    fn tap_cksfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "1.txt 00000000\n2.txt 3625A74A\n3.txt 00000000\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--format", "tap", "-g", "list.sfv"])
+            .fails()
+            .and()
+            .stdout()
+            .is(dedent!(
+                r#"
+                # list.sfv
+                not ok 1 - 1.txt
+                # different CRC: expected 00000000, got 582507A1
+                ok 2 - 2.txt
+                not ok 3 - 3.txt
+                # missing: No such file or directory (os error 2)
+                1..3
+                "#
+            ))
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format csv` quotes paths containing commas or quotes.
+    fn csv_newsfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1,2.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("\"2\".txt"), "Two\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--format", "csv", "1,2.txt", "\"2\".txt"])
+            .succeeds()
+            .and()
+            .stdout()
+            .satisfies(
+                |out| {
+                    let rows = out
+                        .lines()
+                        .map(|l| l.rsplitn(3, ',').nth(2).unwrap())
+                        .collect::<Vec<_>>();
+                    rows == [
+                        "listing,path,size,expected,actual,status,error",
+                        r#","1,2.txt",4,,582507A1,ok,"#,
+                        r#","""2"".txt",4,,3625A74A,ok,"#,
+                    ]
+                },
+                "unexpected rows",
+            )
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format tsv` writes a row for each entry of a listing.
+    fn tsv_cksfv() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--format", "tsv", "-g", &data("12.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("tests/data/12.sfv\t1.txt\t4\t582507A1\t582507A1\tok\t\t")
+            .and()
+            .stdout()
+            .contains("tests/data/12.sfv\t2.txt\t4\t3625A74A\t3625A74A\tok\t\t")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--format html` lists the failures of each listing.
+    fn html_cksfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "1.txt 00000000\n<2>.txt 3625A74A\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--format", "html", "-g", "list.sfv"])
+            .fails()
+            .and()
+            .stdout()
+            .contains("<h2>list.sfv</h2>")
+            .and()
+            .stdout()
+            .contains(r#"<tr><td>1.txt</td><td class="mismatch">mismatch</td><td class="mono">00000000</td><td class="mono">582507A1</td><td></td></tr>"#)
+            .and()
+            .stdout()
+            .contains(r#"<tr><td>&lt;2&gt;.txt</td><td class="missing">missing</td>"#)
+            .and()
+            .stdout()
+            .contains("<tr><th>Bytes</th><td>4</td></tr>")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--color always` colors each result line.
+    fn color_always() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(
+            dir.path().join("list.sfv"),
+            "1.txt 582507A1\n2.txt 00000000\n",
+        )
+        .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--color", "always", "-q", "-g", "list.sfv"])
+            .fails()
+            .and()
+            .stderr()
+            .is("\x1b[1;31m2.txt                                             different CRC\x1b[0m")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--color never` leaves the output untouched.
+    fn color_never() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--color", "never", "-c", "-g", &data("1.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .is(dedent!(
+                r#"
+                1.txt                                             OK
+                Everything OK
+                "#
+            ))
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--stats` summarizes the files checked.
+    fn stats_cksfv() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--stats", "-c", "-g", &data("12.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("Everything OK\nStatistics:\n")
+            .and()
+            .stdout()
+            .contains("  Files checked:  2 (2 OK, 0 different CRC, 0 missing, 0 errors)\n")
+            .and()
+            .stdout()
+            .contains("  Total bytes:    8 (8 B)\n")
+            .and()
+            .stdout()
+            .contains("  Slowest files:\n")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--stats` writes a subtotal for each listing in recursive mode.
+    fn stats_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("a")).unwrap();
+        std::fs::write(dir.path().join("a").join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("a").join("a.sfv"), "1.txt 582507A1\n").unwrap();
+        std::fs::write(dir.path().join("2.txt"), "Two\n").unwrap();
+        std::fs::write(dir.path().join("b.sfv"), "2.txt 00000000\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--stats", "-r"])
+            .fails()
+            .and()
+            .stderr()
+            .contains("Subtotal for b.sfv:\n  Files checked:  1 (0 OK, 1 different CRC, 0 missing, 0 errors)")
+            .and()
+            .stderr()
+            .contains("Subtotal for a/a.sfv:\n  Files checked:  1 (1 OK, 0 different CRC, 0 missing, 0 errors)\n  Total bytes:    4 (4 B)\n  Hashing time:   ")
+            .and()
+            .stderr()
+            .contains("Statistics:\n  Files checked:  2 (1 OK, 1 different CRC, 0 missing, 0 errors)")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that the exit code combines the classes of failures.
+    fn exit_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("a.sfv"), "1.txt 00000000\n2.txt 00000000\n").unwrap();
+        std::fs::write(dir.path().join("b.sfv"), "invalid\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-g", "a.sfv"])
+            .fails_with(3)
+            .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-g", "b.sfv"])
+            .fails_with(8)
+            .unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--invalid-flag"])
+            .fails_with(64)
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that a missing file sets the exit code in quiet mode as well.
+    fn exit_codes_quiet() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.sfv"), "1.txt 00000000\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-q", "-g", "a.sfv"])
+            .fails_with(2)
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--legacy-exit-codes` only exits with `1` on failure.
+    fn exit_codes_legacy() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.sfv"), "1.txt 00000000\n2.txt 00000000\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["--legacy-exit-codes", "-g", "a.sfv"])
+            .fails_with(1)
+            .unwrap();
+    }
+
+    #[test]
+    /// Check that `--printf` formats each generated line.
+    fn printf_newsfv() {
+        assert_cli::Assert::main_binary()
+            .with_args(&[
+                "--printf",
+                "%b\\t%c\\t%s %S %%",
+                &data("1.txt"),
+                &data("2.txt"),
+            ])
+            .succeeds()
+            .and()
+            .stdout()
+            .is("1.txt\t582507A1\t4 ok %\n2.txt\t3625A74A\t4 ok %")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--printf` formats each verification result line.
+    fn printf_cksfv() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("a.sfv"), "1.txt 00000000\n2.txt 00000001\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-c", "--printf", "%p:%S:%e:%c", "-g", "a.sfv"])
+            .fails()
+            .and()
+            .stdout()
+            .is(dedent!(
+                r#"
+                1.txt:mismatch:00000000:582507A1
+                2.txt:missing:00000001:
+                Errors Occured
+                "#
+            ))
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--printf` also formats the lines of unlisted files.
+    fn printf_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("1.txt"), "One\n").unwrap();
+        std::fs::write(dir.path().join("3.txt"), "Three\n").unwrap();
+        std::fs::write(dir.path().join("a.sfv"), "1.txt 582507A1\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-c", "--orphans", "--printf", "%p:%S:%s", "-g", "a.sfv"])
+            .succeeds()
+            .and()
+            .stdout()
+            .is(dedent!(
+                r#"
+                1.txt:ok:4
+                3.txt:unlisted:6
+                Everything OK
+                "#
+            ))
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--printf` rejects unknown placeholders.
+    fn printf_invalid() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--printf", "%z", &data("1.txt")])
+            .fails()
+            .and()
+            .stderr()
+            .contains("invalid placeholder: %z")
+            .unwrap()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Check that `--report-fd` rejects a closed file descriptor.
+    fn report_fd_closed() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--format", "ndjson", "--report-fd", "57", &data("1.txt")])
+            .fails()
+            .and()
+            .stderr()
+            .contains("invalid file descriptor 57")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that a listing with an invalid line is reported as an error.
+    fn invalid_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("bad.sfv"), "1.txt\n").unwrap();
+        binary()
+            .current_dir(dir.path())
+            .with_args(&["-f", "bad.sfv"])
+            .fails()
+            .and()
+            .stderr()
+            .contains("cksfv: bad.sfv: line 1: invalid entry")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--orphans` reports unlisted files without failing.
+    fn orphans() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--orphans", "-c", "-g", &data("1.sfv")])
+            .succeeds()
+            .and()
+            .stdout()
+            .contains("2.txt                                             Not listed")
+            .and()
+            .stdout()
+            .contains("Everything OK")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--strict` fails when unlisted files are found.
+    fn strict() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--strict", "-c", "-g", &data("1.sfv")])
+            .fails()
+            .and()
+            .stdout()
+            .contains("2.txt                                             Not listed")
+            .and()
+            .stdout()
+            .contains("Errors Occured")
+            .unwrap()
+    }
+
+    #[test]
+    /// Check that `--bwlimit` rejects invalid rates.
+    fn bwlimit_invalid() {
+        assert_cli::Assert::main_binary()
+            .with_args(&["--bwlimit", "fast", &data("1.txt")])
+            .fails()
+            .unwrap()
+    }
+}